@@ -1,41 +1,853 @@
 //! Process command line arguments.
 
+use std::env::{args_os, var_os};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, read_to_string};
+use std::io;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use clap::{app_from_crate, crate_authors, crate_description, crate_name, crate_version};
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches, ErrorKind};
+
+use crate::state::{load_last_run, LastRun};
+use crate::template::{
+    validate_date_format, validate_template, DEFAULT_DATE_FORMAT, DEFAULT_TEMPLATE,
+};
+use crate::util::{cache_file, capture_file_name, is_executable};
+use crate::Options;
+
+/// Arguments that can be set from an env file or the config file, with
+/// whether each takes a value.
+///
+/// Arguments that do something other than capture or only make sense for a
+/// single run, such as `--output` or `--dry-run`, aren't settings.
+const SETTINGS: &[(&str, bool)] = &[
+    ("adaptive-quality", false),
+    ("audio-delay", true),
+    ("audio-source", true),
+    ("bitrate", true),
+    ("clipboard", false),
+    ("concat-screens", true),
+    ("contact-sheet", false),
+    ("contact-sheet-grid", true),
+    ("contact-sheet-width", true),
+    ("crf", true),
+    ("cursor", false),
+    ("cursor-fallback", false),
+    ("delay", true),
+    ("desktop-audio", false),
+    ("duration", true),
+    ("duration-from-selection", false),
+    ("exclude-struts", false),
+    ("ffmpeg-path", true),
+    ("format", true),
+    ("include-shadow", false),
+    ("indicator", false),
+    ("input-buffer", true),
+    ("input-sync", true),
+    ("interval", true),
+    ("limit-framerate", false),
+    ("log", true),
+    ("lut", true),
+    ("mix-audio", false),
+    ("mode", true),
+    ("monitor", true),
+    ("name", true),
+    ("native", false),
+    ("no-audio", false),
+    ("no-clobber", false),
+    ("no-cursor", false),
+    ("no-retry-encoder", false),
+    ("no-shadow", false),
+    ("no-show-region", false),
+    ("notify", false),
+    ("offset", true),
+    ("offset-correct", true),
+    ("overwrite", false),
+    ("pointer-at", true),
+    ("preset", true),
+    ("print-path", false),
+    ("progress", true),
+    ("quiet", false),
+    ("rate", true),
+    ("rclone-link", false),
+    ("rclone-remote", true),
+    ("region", true),
+    ("region-geometry", true),
+    ("repeat", true),
+    ("retry-encoder", false),
+    ("safe-mode", false),
+    ("scale", true),
+    ("screenshot-tool", true),
+    ("show-region", false),
+    ("split-av", false),
+    ("startup-check", false),
+    ("stop-after-idle", true),
+    ("timestamp-format", true),
+    ("tonemap", false),
+    ("upload", true),
+    ("vcodec", true),
+    ("verbose", false),
+    ("watch-speed", false),
+    ("watermark-font", true),
+    ("watermark-opacity", true),
+    ("watermark-position", true),
+    ("watermark-size", true),
+    ("watermark-text", true),
+    ("webcam", true),
+    ("webcam-position", true),
+    ("window-name", true),
+];
+
+/// Settings that are only given by a short flag.
+const SHORT_SETTINGS: &[(&str, char)] = &[("mode", 'm'), ("rate", 'R'), ("region", 'r')];
+
+/// The highest framerate video can be recorded at.
+const MAX_FRAMERATE: u64 = 240;
+
+/// Arguments that need tools other than ffmpeg, so can't be used in safe mode.
+const SAFE_MODE_CONFLICTS: &[&str] = &[
+    "monitor",
+    "webcam",
+    "concat-screens",
+    "exclude-struts",
+    "indicator",
+    "rclone-remote",
+    "stop-after-idle",
+    "cursor-fallback",
+    "limit-framerate",
+    "desktop-audio",
+];
+
+/// Arguments that save or use the capture as a file, so can't be used when
+/// writing video to standard output.
+const STDOUT_CONFLICTS: &[&str] = &[
+    "cursor-fallback",
+    "record-stdin-commands",
+    "split-av",
+    "progress",
+    "contact-sheet",
+    "clipboard",
+    "notify",
+    "rclone-remote",
+    "upload",
+];
+
+/// Arguments for recording video that don't apply to GIFs.
+const GIF_CONFLICTS: &[&str] = &[
+    "concat-screens",
+    "cursor-fallback",
+    "record-stdin-commands",
+    "stop-after-idle",
+    "duration-from-selection",
+    "retry-encoder",
+    "no-retry-encoder",
+    "watch-speed",
+    "progress",
+    "input-sync",
+    "input-buffer",
+    "indicator",
+    "tonemap",
+    "lut",
+    "scale",
+    "watermark-text",
+    "webcam",
+    "adaptive-quality",
+    "vcodec",
+    "limit-framerate",
+    "crf",
+    "bitrate",
+    "preset",
+    "split-av",
+    "mix-audio",
+    "desktop-audio",
+    "no-audio",
+    "audio-source",
+    "audio-delay",
+];
+
+/// Arguments for capturing the screen that don't apply to recording audio.
+const AUDIO_CONFLICTS: &[&str] = &[
+    "print-region",
+    "region-geometry",
+    "window-name",
+    "monitor",
+    "concat-screens",
+    "exclude-struts",
+    "offset-correct",
+    "offset",
+    "native",
+    "safe-mode",
+    "pointer-at",
+    "cursor",
+    "cursor-fallback",
+    "show-region",
+    "record-stdin-commands",
+    "stop-after-idle",
+    "duration-from-selection",
+    "retry-encoder",
+    "no-retry-encoder",
+    "watch-speed",
+    "progress",
+    "input-sync",
+    "input-buffer",
+    "indicator",
+    "tonemap",
+    "lut",
+    "scale",
+    "watermark-text",
+    "webcam",
+    "contact-sheet",
+    "adaptive-quality",
+    "vcodec",
+    "limit-framerate",
+    "crf",
+    "bitrate",
+    "preset",
+    "format",
+    "split-av",
+    "no-audio",
+];
+
+/// Extensions of the 3D LUT files ffmpeg can read.
+const LUT_EXTENSIONS: &[&str] = &["cube", "3dl", "dat", "m3d", "csp"];
+
+/// Extensions of video containers with the ffmpeg format for each.
+const VIDEO_CONTAINERS: &[(&str, &str)] = &[
+    ("mkv", "matroska"),
+    ("mp4", "mp4"),
+    ("mov", "mov"),
+    ("webm", "webm"),
+];
+
+/// The config file written by `--write-config`, listing the common settings.
+const DEFAULT_CONFIG: &str = r#"# screencap configuration
+#
+# Settings here are used unless they are given on the command line or in an
+# env file. Keys are the long names of options, such as audio-source, and flags
+# take true or false. Remove the # from a setting to use it.
+
+# The region to capture: screen, window, select or active-output.
+# region = "screen"
+
+# Whether to capture an image, video or gif.
+# mode = "image"
+
+# The framerate when capturing video or GIFs.
+# rate = 30
+
+# The ffmpeg video encoder to record with.
+# vcodec = "libx264"
+
+# The PulseAudio source to record audio from.
+# audio-source = "default"
+"#;
+
+/// The largest audio delay in milliseconds, either way.
+const MAX_AUDIO_DELAY: i64 = 10_000;
 
 /// Configuration from command line.
 #[derive(Debug, Default)]
 pub struct Config {
     region: ScreenRegion,
     mode: CaptureMode,
+    rclone_remote: Option<String>,
+    rclone_link: bool,
+    input_sync: Option<InputSync>,
+    pointer_at: Option<(u32, u32)>,
+    probe_only: bool,
+    startup_check: bool,
+    print_region: bool,
+    json: bool,
+    cursor_fallback: bool,
+    stop_after_idle: Option<u64>,
+    concat_screens: Option<Vec<MonitorId>>,
+    retry_encoder: bool,
+    name_template: String,
+    timestamp_format: String,
+    adaptive_quality: bool,
+    native: bool,
+    duration_from_selection: bool,
+    output: Option<PathBuf>,
+    image_format: ImageFormat,
+    indicator: bool,
+    offset_correct: Option<OffsetCorrection>,
+    offset: (i32, i32),
+    benchmark_encoders: bool,
+    exclude_struts: bool,
+    record_stdin_commands: bool,
+    tonemap: bool,
+    split_av: bool,
+    monitor: Option<MonitorId>,
+    audio_delay: Option<i64>,
+    ffmpeg_path: Option<PathBuf>,
+    check_compat: Option<(String, String, String)>,
+    watch_speed: bool,
+    progress: Option<ProgressFormat>,
+    safe_mode: bool,
+    duration: Option<u64>,
+    no_shadow: bool,
+    lut: Option<PathBuf>,
+    scale: Option<String>,
+    args: Vec<OsString>,
+    geometry: Option<(String, String)>,
+    watermark: Option<Watermark>,
+    webcam: Option<Webcam>,
+    input_buffer: Option<u64>,
+    contact_sheet: Option<ContactSheet>,
+    container: Option<&'static str>,
+    list_codecs: bool,
+    vcodec: Option<String>,
+    no_audio: bool,
+    audio_source: Option<String>,
+    list_audio_sources: bool,
+    mix_audio: bool,
+    desktop_audio: bool,
+    crf: Option<u32>,
+    bitrate: Option<u64>,
+    preset: Option<String>,
+    clipboard: bool,
+    notify: bool,
+    delay: u64,
+    repeat: u64,
+    interval: u64,
+    write_config: bool,
+    screenshot_tool: Option<ScreenshotTool>,
+    log: Option<PathBuf>,
+    dry_run: bool,
+    limit_framerate: bool,
+    cursor: Option<bool>,
+    show_region: bool,
+    refresh_caps: bool,
+    print_path: bool,
+    upload: Option<String>,
+    verbosity: u64,
+    quiet: bool,
+    window_name: Option<String>,
+    fps_probe: bool,
+    overwrite: bool,
+    interactive: bool,
 }
 
 impl Config {
     /// Process configuration from command line.
-    pub fn from_args() -> Self {
-        let matches = Config::args().get_matches();
+    pub fn from_args() -> Result<Self, clap::Error> {
+        let matches = Config::args().get_matches_safe()?;
+        Config::from_matches(matches, args_os().collect())
+    }
+
+    /// Configuration for capturing an image as a library, with the defaults
+    /// of the command and without reading any settings.
+    pub fn for_image(output: &Path, region: ScreenRegion) -> Result<Self, clap::Error> {
+        Config::for_capture(output, region, Image)
+    }
+
+    /// Configuration for recording video as a library.
+    pub fn for_video(
+        output: &Path,
+        region: ScreenRegion,
+        options: &Options,
+    ) -> Result<Self, clap::Error> {
+        if !(1..=MAX_FRAMERATE).contains(&options.framerate) {
+            return Err(invalid(&format!(
+                "{} is not a framerate from 1 to {}",
+                options.framerate, MAX_FRAMERATE
+            )));
+        }
+        Ok(Config {
+            duration: options.duration,
+            no_audio: !options.audio,
+            cursor: Some(options.cursor),
+            vcodec: options.vcodec.clone(),
+            ..Config::for_capture(output, region, Video(options.framerate))?
+        })
+    }
+
+    fn for_capture(
+        output: &Path,
+        region: ScreenRegion,
+        mode: CaptureMode,
+    ) -> Result<Self, clap::Error> {
+        if output == Path::new("-") {
+            return Err(conflict(
+                "Can only write to standard output from the command",
+            ));
+        }
+        capture_file_name(output).map_err(|e| invalid(&e.to_string()))?;
+        if let Geometry { width: 0, .. } | Geometry { height: 0, .. } = region {
+            return Err(invalid("Cannot capture an empty region"));
+        }
+        let image_format = match mode {
+            Image => resolve_image_format(None, Some(output)).map_err(|e| conflict(&e))?,
+            _ => ImageFormat::default(),
+        };
+        let container = resolve_container(mode, None, Some(output)).map_err(|e| conflict(&e))?;
+
+        Ok(Config {
+            mode,
+            region,
+            output: Some(output.to_owned()),
+            image_format,
+            container,
+            retry_encoder: true,
+            name_template: DEFAULT_TEMPLATE.to_owned(),
+            timestamp_format: DEFAULT_DATE_FORMAT.to_owned(),
+            repeat: 1,
+            interval: 1,
+            show_region: true,
+            ..Config::default()
+        })
+    }
+
+    fn from_matches(
+        matches: ArgMatches<'static>,
+        args: Vec<OsString>,
+    ) -> Result<Self, clap::Error> {
+        // A repeated capture is run with the arguments of the last capture
+        // in place of the command line.
+        let (matches, args, geometry) = if matches.is_present("repeat-last") {
+            let LastRun { args, geometry } = load_last_run()?;
+            (Config::args().get_matches_from_safe(&args)?, args, geometry)
+        } else {
+            (matches, args, None)
+        };
+
+        // Settings from an env file are passed as though they came before the
+        // command line so that they are validated the same way.
+        let (matches, args) = match matches.value_of("env-file") {
+            Some(env_file) => {
+                let args = with_settings(env_file_args(env_file, &matches)?, args);
+                (Config::args().get_matches_from_safe(&args)?, args)
+            }
+            None => (matches, args),
+        };
+
+        // Settings from the config file come first so that everything else
+        // overrides them.
+        let (matches, args) = match config_file().filter(|path| path.exists()) {
+            Some(config_file) => {
+                let args = with_settings(config_file_args(&config_file, &matches)?, args);
+                (Config::args().get_matches_from_safe(&args)?, args)
+            }
+            None => (matches, args),
+        };
+
+        Config::from_settled(matches, args, geometry)
+    }
 
+    /// Check and process the arguments once every setting is in place.
+    fn from_settled(
+        matches: ArgMatches<'static>,
+        args: Vec<OsString>,
+        geometry: Option<(String, String)>,
+    ) -> Result<Self, clap::Error> {
         let mode = match matches.value_of("mode").unwrap() {
             "image" => Image,
-            "video" => Video(matches.value_of("rate").unwrap().parse().unwrap()),
+            "video" => Video(parse_arg(&matches, "rate", str::parse)?.unwrap()),
+            "gif" => Gif(parse_arg(&matches, "rate", str::parse)?.unwrap()),
+            "audio" => Audio,
             _ => unreachable!(),
         };
 
-        let region = matches.value_of("region").unwrap().parse().unwrap();
+        let region = match parse_arg(&matches, "region-geometry", str::parse)? {
+            Some(_) if matches.occurrences_of("region") > 0 => {
+                return Err(conflict("Cannot use -r with --region-geometry"));
+            }
+            Some(region) => region,
+            None => parse_arg(&matches, "region", str::parse)?.unwrap(),
+        };
+
+        // Windows found by name are captured like the focused window.
+        let window_name = matches.value_of("window-name").map(str::to_owned);
+        let region = match (&window_name, region) {
+            (Some(_), Window) => Window,
+            (Some(_), _) if matches.occurrences_of("region") > 0 => {
+                return Err(conflict("Can only use --window-name with -r window"));
+            }
+            (Some(_), Geometry { .. }) => {
+                return Err(conflict("Cannot use --window-name with --region-geometry"));
+            }
+            (Some(_), _) => Window,
+            (None, region) => region,
+        };
 
         // Basic validation of particular combinations.
-        let (mode, region) = match (mode, region) {
-            // TODO: Add proper errors.
-            (Video(_), Select) => panic!("Cannot select region for video capture"),
-            (mode, region) => (mode, region),
+        if let Gif(_) = mode {
+            for name in GIF_CONFLICTS {
+                if matches.is_present(name) {
+                    return Err(conflict(&format!("Cannot use --{} for GIF capture", name)));
+                }
+            }
+        }
+        if let Audio = mode {
+            for name in AUDIO_CONFLICTS {
+                if matches.is_present(name) {
+                    return Err(conflict(&format!(
+                        "Cannot use --{} when recording audio",
+                        name
+                    )));
+                }
+            }
+        }
+
+        let rclone_remote = matches.value_of("rclone-remote").map(str::to_owned);
+        let rclone_link = matches.is_present("rclone-link");
+
+        let input_sync = parse_arg(&matches, "input-sync", str::parse)?;
+
+        let cursor = match (
+            matches.is_present("cursor"),
+            matches.is_present("no-cursor"),
+        ) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+        if cursor == Some(false) && matches.is_present("cursor-fallback") {
+            return Err(conflict(
+                "Cannot draw the cursor afterwards without a cursor",
+            ));
+        }
+
+        let pointer_at = parse_arg(&matches, "pointer-at", parse_point)?;
+        if let (Video(_) | Gif(_), Some(_)) = (mode, pointer_at) {
+            return Err(conflict("Cannot place pointer for video capture"));
+        }
+
+        let concat_screens = if matches.is_present("concat-screens") {
+            let screens = matches.values_of("concat-screens").into_iter().flatten();
+            let screens = screens.map(|screen| {
+                screen
+                    .parse()
+                    .map_err(|_| invalid(&format!("{:?} is not a monitor", screen)))
+            });
+            Some(screens.collect::<Result<_, _>>()?)
+        } else {
+            None
+        };
+        match (mode, &concat_screens) {
+            (Image, Some(_)) => {
+                return Err(conflict("Cannot concatenate screens for image capture"))
+            }
+            (_, Some(_)) if matches.is_present("cursor-fallback") => {
+                return Err(conflict(
+                    "Cannot use the cursor fallback when concatenating screens",
+                ))
+            }
+            _ => {}
+        }
+
+        let monitor: Option<MonitorId> = parse_arg(&matches, "monitor", str::parse)?;
+        if monitor.is_some() {
+            if let Window | Select | ActiveOutput | AllScreens | Geometry { .. } = region {
+                return Err(conflict("Can only select a monitor for screen capture"));
+            }
+            if concat_screens.is_some() {
+                return Err(conflict(
+                    "Cannot select a monitor when concatenating screens",
+                ));
+            }
+            if matches.is_present("exclude-struts") {
+                return Err(conflict("Cannot exclude struts when selecting a monitor"));
+            }
+        }
+
+        let native = matches.is_present("native");
+        match (mode, region, native) {
+            (Video(_) | Gif(_), _, true) => {
+                return Err(conflict("Cannot use native capture for video"))
+            }
+            (_, Select, true) => return Err(conflict("Cannot select region for native capture")),
+            _ => {}
+        }
+
+        let log = match matches.value_of_os("log") {
+            Some(path) => Some(PathBuf::from(path)),
+            None if matches.is_present("verbose") => Some(
+                cache_file("last.log")
+                    .ok_or_else(|| invalid("No HOME to write the log to, use --log"))?,
+            ),
+            None => None,
+        };
+
+        let screenshot_tool = parse_arg(&matches, "screenshot-tool", str::parse)?;
+
+        let output = matches.value_of("output").map(PathBuf::from);
+        let to_stdout = output.as_deref() == Some(Path::new("-"));
+        if let Some(output) = output.as_deref().filter(|_| !to_stdout) {
+            capture_file_name(output).map_err(|e| invalid(&e.to_string()))?;
+        }
+        if to_stdout {
+            if matches.is_present("print-path") {
+                return Err(conflict(
+                    "Cannot print the path when writing to standard output",
+                ));
+            }
+            if !matches!(mode, Video(_)) {
+                return Err(conflict("Can only write video to standard output"));
+            }
+            for name in STDOUT_CONFLICTS {
+                if matches.is_present(name) {
+                    return Err(conflict(&format!(
+                        "Cannot use --{} when writing to standard output",
+                        name
+                    )));
+                }
+            }
+        }
+
+        let (image_format, container) = if to_stdout {
+            let container = matches
+                .value_of("format")
+                .map(video_container)
+                .transpose()
+                .map_err(|message| invalid(&message))?;
+            (ImageFormat::default(), container)
+        } else if let Video(_) = mode {
+            let container = resolve_container(mode, matches.value_of("format"), output.as_deref())
+                .map_err(|message| conflict(&message))?;
+            (ImageFormat::default(), container)
+        } else if let Audio = mode {
+            resolve_container(mode, None, output.as_deref()).map_err(|e| conflict(&e))?;
+            (ImageFormat::default(), None)
+        } else {
+            if let (Gif(_), true) = (mode, matches.is_present("format")) {
+                return Err(conflict("Cannot set the format for GIF capture"));
+            }
+            let image_format = resolve_image_format(matches.value_of("format"), output.as_deref())
+                .map_err(|message| conflict(&message))?;
+            let container =
+                resolve_container(mode, None, output.as_deref()).map_err(|e| conflict(&e))?;
+            (image_format, container)
         };
 
-        Config {
-            mode: mode,
-            region: region,
+        let record_stdin_commands = matches.is_present("record-stdin-commands");
+        if record_stdin_commands {
+            if let Image = mode {
+                return Err(conflict("Cannot record commands for image capture"));
+            }
+            for name in &[
+                "cursor-fallback",
+                "stop-after-idle",
+                "duration-from-selection",
+                "split-av",
+                "progress",
+                "duration",
+            ] {
+                if matches.is_present(name) {
+                    return Err(conflict(&format!(
+                        "Cannot use --{} with commands from standard input",
+                        name
+                    )));
+                }
+            }
+        }
+
+        let progress = parse_arg(&matches, "progress", str::parse)?;
+        if let (Image, Some(_)) = (mode, progress) {
+            return Err(conflict("Cannot report progress for image capture"));
+        }
+
+        let duration = parse_arg(&matches, "duration", parse_duration)?;
+        if let (Image, Some(_)) = (mode, duration) {
+            return Err(conflict("Cannot set a duration for image capture"));
+        }
+
+        let split_av = matches.is_present("split-av");
+        if let (Image, true) = (mode, split_av) {
+            return Err(conflict("Cannot split audio from image capture"));
+        }
+
+        let mix_audio = matches.is_present("mix-audio");
+        if let (Image, true) = (mode, mix_audio) {
+            return Err(conflict("Cannot mix audio for image capture"));
+        }
+
+        let desktop_audio = matches.is_present("desktop-audio");
+        if let (Image, true) = (mode, desktop_audio) {
+            return Err(conflict("Cannot record desktop audio for image capture"));
+        }
+
+        let no_audio = matches.is_present("no-audio");
+        if no_audio {
+            if let Image = mode {
+                return Err(conflict("Cannot leave out audio from image capture"));
+            }
+            for name in &[
+                "split-av",
+                "audio-delay",
+                "audio-source",
+                "mix-audio",
+                "desktop-audio",
+            ] {
+                if matches.is_present(name) {
+                    return Err(conflict(&format!("Cannot use --{} without audio", name)));
+                }
+            }
+        }
+
+        let repeat = parse_arg(&matches, "repeat", u64::from_str)?;
+        if repeat.is_some() {
+            if !matches!(mode, Image) {
+                return Err(conflict("Can only repeat image capture"));
+            }
+            for name in &["clipboard", "dry-run"] {
+                if matches.is_present(name) {
+                    return Err(conflict(&format!(
+                        "Cannot use --{} when repeating captures",
+                        name
+                    )));
+                }
+            }
+        }
+
+        let safe_mode = matches.is_present("safe-mode");
+        if safe_mode {
+            if let Window | Select | ActiveOutput = region {
+                return Err(conflict("Can only capture the screen in safe mode"));
+            }
+            for name in SAFE_MODE_CONFLICTS {
+                if matches.is_present(name) {
+                    return Err(conflict(&format!("Cannot use --{} in safe mode", name)));
+                }
+            }
+            let templates = matches
+                .value_of("name")
+                .into_iter()
+                .chain(matches.value_of("watermark-text"));
+            for template in templates {
+                for placeholder in &["{clipboard}", "{selection}", "{git-branch}"] {
+                    if template.contains(placeholder) {
+                        return Err(conflict(&format!(
+                            "Cannot use {} in safe mode",
+                            placeholder
+                        )));
+                    }
+                }
+            }
         }
+
+        let watermark = match matches.value_of("watermark-text") {
+            Some(text) => Some(Watermark {
+                text: text.to_owned(),
+                position: parse_arg(&matches, "watermark-position", str::parse)?.unwrap(),
+                opacity: parse_arg(&matches, "watermark-opacity", str::parse)?.unwrap(),
+                size: parse_arg(&matches, "watermark-size", str::parse)?.unwrap(),
+                font: matches.value_of("watermark-font").map(PathBuf::from),
+            }),
+            None => None,
+        };
+
+        let webcam = match matches.value_of_os("webcam") {
+            Some(_) if matches!(mode, Image) => {
+                return Err(conflict("Cannot overlay a webcam for image capture"));
+            }
+            Some(device) => Some(Webcam {
+                device: PathBuf::from(device),
+                position: parse_arg(&matches, "webcam-position", str::parse)?.unwrap(),
+            }),
+            None => None,
+        };
+
+        let contact_sheet = if matches.is_present("contact-sheet") {
+            if let Image = mode {
+                return Err(conflict("Cannot make a contact sheet for image capture"));
+            }
+            let (columns, rows) = parse_arg(&matches, "contact-sheet-grid", parse_grid)?.unwrap();
+            Some(ContactSheet {
+                columns,
+                rows,
+                width: parse_arg(&matches, "contact-sheet-width", str::parse)?.unwrap(),
+            })
+        } else {
+            None
+        };
+
+        Ok(Config {
+            mode,
+            region,
+            rclone_remote,
+            rclone_link,
+            input_sync,
+            pointer_at,
+            probe_only: matches.is_present("probe-only"),
+            startup_check: matches.is_present("startup-check"),
+            print_region: matches.is_present("print-region"),
+            json: matches.is_present("json"),
+            cursor_fallback: matches.is_present("cursor-fallback"),
+            stop_after_idle: parse_arg(&matches, "stop-after-idle", parse_duration)?,
+            concat_screens,
+            retry_encoder: !matches.is_present("no-retry-encoder"),
+            name_template: matches.value_of("name").unwrap().to_owned(),
+            timestamp_format: matches.value_of("timestamp-format").unwrap().to_owned(),
+            adaptive_quality: matches.is_present("adaptive-quality"),
+            native,
+            duration_from_selection: matches.is_present("duration-from-selection"),
+            output,
+            image_format,
+            indicator: matches.is_present("indicator"),
+            offset_correct: parse_arg(&matches, "offset-correct", str::parse)?,
+            offset: parse_arg(&matches, "offset", parse_offset)?.unwrap_or((0, 0)),
+            benchmark_encoders: matches.is_present("benchmark-encoders"),
+            exclude_struts: matches.is_present("exclude-struts"),
+            record_stdin_commands,
+            tonemap: matches.is_present("tonemap"),
+            split_av,
+            monitor,
+            audio_delay: parse_arg(&matches, "audio-delay", str::parse)?,
+            ffmpeg_path: matches.value_of("ffmpeg-path").map(PathBuf::from),
+            check_compat: matches.values_of("check-compat").map(|mut values| {
+                let mut next = || values.next().unwrap().to_owned();
+                (next(), next(), next())
+            }),
+            watch_speed: matches.is_present("watch-speed"),
+            progress,
+            safe_mode,
+            duration,
+            no_shadow: matches.is_present("no-shadow"),
+            lut: matches.value_of("lut").map(PathBuf::from),
+            scale: matches.value_of("scale").map(str::to_owned),
+            args,
+            geometry,
+            watermark,
+            webcam,
+            input_buffer: parse_arg(&matches, "input-buffer", str::parse)?,
+            contact_sheet,
+            container,
+            list_codecs: matches.is_present("list-codecs"),
+            vcodec: matches.value_of("vcodec").map(str::to_owned),
+            no_audio,
+            audio_source: matches.value_of("audio-source").map(str::to_owned),
+            list_audio_sources: matches.is_present("list-audio-sources"),
+            mix_audio,
+            desktop_audio,
+            crf: parse_arg(&matches, "crf", str::parse)?,
+            bitrate: parse_arg(&matches, "bitrate", parse_bitrate)?,
+            preset: matches.value_of("preset").map(str::to_owned),
+            clipboard: matches.is_present("clipboard"),
+            notify: matches.is_present("notify"),
+            delay: parse_arg(&matches, "delay", parse_duration)?.unwrap(),
+            repeat: repeat.unwrap_or(1),
+            interval: parse_arg(&matches, "interval", parse_duration)?.unwrap_or(1),
+            write_config: matches.is_present("write-config"),
+            screenshot_tool,
+            log,
+            dry_run: matches.is_present("dry-run"),
+            limit_framerate: matches.is_present("limit-framerate"),
+            cursor,
+            show_region: !matches.is_present("no-show-region"),
+            refresh_caps: matches.is_present("refresh-caps"),
+            print_path: matches.is_present("print-path"),
+            upload: matches.value_of("upload").map(str::to_owned),
+            verbosity: matches.occurrences_of("verbose"),
+            quiet: matches.is_present("quiet"),
+            window_name,
+            fps_probe: matches.is_present("fps-probe"),
+            overwrite: matches.is_present("overwrite"),
+            interactive: true,
+        })
     }
 
     pub fn mode(&self) -> CaptureMode {
@@ -46,50 +858,1986 @@ impl Config {
         self.region
     }
 
+    /// The rclone remote (`remote:path`) to copy captures to.
+    pub fn rclone_remote(&self) -> Option<&str> {
+        self.rclone_remote.as_deref()
+    }
+
+    /// Whether to print a public link for captures copied with rclone.
+    pub fn rclone_link(&self) -> bool {
+        self.rclone_link
+    }
+
+    /// The timing source for captured frames, if not ffmpeg's default.
+    pub fn input_sync(&self) -> Option<InputSync> {
+        self.input_sync
+    }
+
+    /// Where to draw the pointer in a captured image.
+    pub fn pointer_at(&self) -> Option<(u32, u32)> {
+        self.pointer_at
+    }
+
+    /// Whether to report what would be captured instead of capturing.
+    pub fn probe_only(&self) -> bool {
+        self.probe_only
+    }
+
+    /// Whether to check the tools the capture needs are installed first.
+    pub fn startup_check(&self) -> bool {
+        self.startup_check
+    }
+
+    /// Whether to print the region that would be captured instead of
+    /// capturing.
+    pub fn print_region(&self) -> bool {
+        self.print_region
+    }
+
+    /// Whether to track and draw the cursor instead of relying on x11grab.
+    pub fn cursor_fallback(&self) -> bool {
+        self.cursor_fallback
+    }
+
+    /// Seconds without keyboard or mouse input after which to stop recording.
+    pub fn stop_after_idle(&self) -> Option<u64> {
+        self.stop_after_idle
+    }
+
+    /// Monitors to tile into a single video, where none means all of them.
+    pub fn concat_screens(&self) -> Option<&[MonitorId]> {
+        self.concat_screens.as_deref()
+    }
+
+    /// Whether to fall back to the next video encoder if one fails to start.
+    pub fn retry_encoder(&self) -> bool {
+        self.retry_encoder
+    }
+
+    /// The template used to name captures.
+    pub fn name_template(&self) -> &str {
+        &self.name_template
+    }
+
+    /// The chrono format `{date}` is replaced with in templates.
+    pub fn timestamp_format(&self) -> &str {
+        &self.timestamp_format
+    }
+
+    /// Whether to choose the video quality from the capture size and framerate.
+    pub fn adaptive_quality(&self) -> bool {
+        self.adaptive_quality
+    }
+
+    /// Whether to capture images directly from the X11 server.
+    pub fn native(&self) -> bool {
+        self.native
+    }
+
+    /// Whether recording starts and stops when Enter is pressed.
+    pub fn duration_from_selection(&self) -> bool {
+        self.duration_from_selection
+    }
+
+    /// The path to save the capture to instead of a generated name.
+    pub fn output(&self) -> Option<&Path> {
+        self.output.as_deref()
+    }
+
+    /// Whether video is written to standard output rather than a file.
+    pub fn to_stdout(&self) -> bool {
+        self.output() == Some(Path::new("-"))
+    }
+
+    /// The format images are saved in.
+    pub fn image_format(&self) -> ImageFormat {
+        self.image_format
+    }
+
+    /// Whether to show an indicator while recording.
+    pub fn indicator(&self) -> bool {
+        self.indicator
+    }
+
+    /// The correction to apply to the position of the capture region.
+    pub fn offset_correct(&self) -> Option<OffsetCorrection> {
+        self.offset_correct
+    }
+
+    /// Pixels to move the x11grab input by after the region is found.
+    pub fn offset(&self) -> (i32, i32) {
+        self.offset
+    }
+
+    /// Whether to compare the available video encoders instead of capturing.
+    pub fn benchmark_encoders(&self) -> bool {
+        self.benchmark_encoders
+    }
+
+    /// Whether screen captures leave out the areas reserved for panels.
+    pub fn exclude_struts(&self) -> bool {
+        self.exclude_struts
+    }
+
+    /// Whether recording is controlled by commands from standard input.
+    pub fn record_stdin_commands(&self) -> bool {
+        self.record_stdin_commands
+    }
+
+    /// Whether to tone map HDR video to SDR.
+    pub fn tonemap(&self) -> bool {
+        self.tonemap
+    }
+
+    /// Whether to write audio to a separate file from the video.
+    pub fn split_av(&self) -> bool {
+        self.split_av
+    }
+
+    /// The monitor to capture instead of the whole screen.
+    pub fn monitor(&self) -> Option<&MonitorId> {
+        self.monitor.as_ref()
+    }
+
+    /// Milliseconds to delay the audio by, where negative values advance it.
+    pub fn audio_delay(&self) -> Option<i64> {
+        self.audio_delay
+    }
+
+    /// The ffmpeg binary to use instead of the one on the PATH.
+    pub fn ffmpeg_path(&self) -> Option<&Path> {
+        self.ffmpeg_path.as_deref()
+    }
+
+    /// The container, video encoder and audio encoder to check work together.
+    pub fn check_compat(&self) -> Option<(&str, &str, &str)> {
+        self.check_compat
+            .as_ref()
+            .map(|(container, video, audio)| (&container[..], &video[..], &audio[..]))
+    }
+
+    /// Whether to warn when encoding can't keep up with recording.
+    pub fn watch_speed(&self) -> bool {
+        self.watch_speed
+    }
+
+    /// How to report recording progress to programs wrapping screencap.
+    pub fn progress(&self) -> Option<ProgressFormat> {
+        self.progress
+    }
+
+    /// Whether to only use ffmpeg and the X11 server for capturing.
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
+    /// Seconds to record video for before stopping.
+    pub fn duration(&self) -> Option<u64> {
+        self.duration
+    }
+
+    /// Whether to leave out the shadow drawn by windows with client side
+    /// decorations.
+    pub fn no_shadow(&self) -> bool {
+        self.no_shadow
+    }
+
+    /// The 3D LUT file to grade captures with.
+    pub fn lut(&self) -> Option<&Path> {
+        self.lut.as_deref()
+    }
+
+    /// The size to scale captures to as ffmpeg's `W:H`.
+    pub fn scale(&self) -> Option<&str> {
+        self.scale.as_deref()
+    }
+
+    /// The arguments the capture was made with, including any from an env
+    /// file.
+    pub fn invocation(&self) -> &[OsString] {
+        &self.args
+    }
+
+    /// The geometry of a repeated capture, to use in place of the region.
+    pub fn geometry(&self) -> Option<&(String, String)> {
+        self.geometry.as_ref()
+    }
+
+    /// The text to mark captures with.
+    pub fn watermark(&self) -> Option<&Watermark> {
+        self.watermark.as_ref()
+    }
+
+    /// The webcam to overlay on recordings.
+    pub fn webcam(&self) -> Option<&Webcam> {
+        self.webcam.as_ref()
+    }
+
+    /// Megabytes of captured frames to buffer for each video input.
+    pub fn input_buffer(&self) -> Option<u64> {
+        self.input_buffer
+    }
+
+    /// The contact sheet to make from a recording.
+    pub fn contact_sheet(&self) -> Option<&ContactSheet> {
+        self.contact_sheet.as_ref()
+    }
+
+    /// The ffmpeg format for video, if given by --format or the output
+    /// extension.
+    pub fn container(&self) -> Option<&'static str> {
+        self.container
+    }
+
+    /// The extension for video saved in the chosen container, which is
+    /// Matroska by default.
+    pub fn video_extension(&self) -> &'static str {
+        VIDEO_CONTAINERS
+            .iter()
+            .find(|&&(_, container)| Some(container) == self.container)
+            .map_or("mkv", |&(extension, _)| extension)
+    }
+
+    /// Whether to list the codecs ffmpeg would be used with instead of
+    /// capturing.
+    pub fn list_codecs(&self) -> bool {
+        self.list_codecs
+    }
+
+    /// The video encoder to record with instead of the preferred one.
+    pub fn vcodec(&self) -> Option<&str> {
+        self.vcodec.as_deref()
+    }
+
+    /// Whether to record video without audio.
+    pub fn no_audio(&self) -> bool {
+        self.no_audio
+    }
+
+    /// The PulseAudio source to record from instead of the default.
+    pub fn audio_source(&self) -> Option<&str> {
+        self.audio_source.as_deref()
+    }
+
+    /// Whether to list the PulseAudio sources instead of capturing.
+    pub fn list_audio_sources(&self) -> bool {
+        self.list_audio_sources
+    }
+
+    /// Whether to mix what is playing into the recorded audio.
+    pub fn mix_audio(&self) -> bool {
+        self.mix_audio
+    }
+
+    /// Whether to record what is playing instead of the default source.
+    pub fn desktop_audio(&self) -> bool {
+        self.desktop_audio
+    }
+
+    /// The CRF to encode video with instead of the default.
+    pub fn crf(&self) -> Option<u32> {
+        self.crf
+    }
+
+    /// The bitrate in bits per second to encode video at instead of a CRF.
+    pub fn bitrate(&self) -> Option<u64> {
+        self.bitrate
+    }
+
+    /// The encoder preset to encode video with instead of the default.
+    pub fn preset(&self) -> Option<&str> {
+        self.preset.as_deref()
+    }
+
+    /// Whether to copy the capture to the clipboard.
+    pub fn clipboard(&self) -> bool {
+        self.clipboard
+    }
+
+    /// Whether to send a desktop notification once the capture is saved.
+    pub fn notify(&self) -> bool {
+        self.notify
+    }
+
+    /// Seconds to count down before capturing.
+    pub fn delay(&self) -> u64 {
+        self.delay
+    }
+
+    /// How many images to capture one after another.
+    pub fn repeat(&self) -> u64 {
+        self.repeat
+    }
+
+    /// Seconds to wait between repeated captures.
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// Whether to write the default config file instead of capturing.
+    pub fn write_config(&self) -> bool {
+        self.write_config
+    }
+
+    /// The tool to take screenshots with on X11, if one was chosen.
+    pub fn screenshot_tool(&self) -> Option<ScreenshotTool> {
+        self.screenshot_tool
+    }
+
+    /// The file to write ffmpeg's output to while recording video, if any.
+    pub fn log(&self) -> Option<&Path> {
+        self.log.as_deref()
+    }
+
+    /// Whether to print the capture command instead of running it.
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Whether to record video no faster than the display refreshes.
+    pub fn limit_framerate(&self) -> bool {
+        self.limit_framerate
+    }
+
+    /// Whether to draw the cursor in video and GIFs.
+    pub fn cursor(&self) -> bool {
+        self.cursor.unwrap_or(true)
+    }
+
+    /// Whether to include the pointer in screenshots, which is only done when
+    /// asked for.
+    pub fn image_cursor(&self) -> bool {
+        self.cursor.unwrap_or(false)
+    }
+
+    /// Whether to draw a border around the region while recording video.
+    pub fn show_region(&self) -> bool {
+        self.show_region
+    }
+
+    /// Whether to probe what ffmpeg supports again rather than use the cache.
+    pub fn refresh_caps(&self) -> bool {
+        self.refresh_caps
+    }
+
+    /// Whether to print only the path of the capture to standard output.
+    pub fn print_path(&self) -> bool {
+        self.print_path
+    }
+
+    /// The shell command to upload the capture with, if any.
+    pub fn upload(&self) -> Option<&str> {
+        self.upload.as_deref()
+    }
+
+    /// How much detail to report, from the number of times `-v` is given.
+    pub fn verbosity(&self) -> u64 {
+        self.verbosity
+    }
+
+    /// Whether to report nothing but errors.
+    pub fn quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// The title or class of the window to capture instead of the focused one.
+    pub fn window_name(&self) -> Option<&str> {
+        self.window_name.as_deref()
+    }
+
+    /// Whether to measure the framerate capture can keep up instead of
+    /// capturing.
+    pub fn fps_probe(&self) -> bool {
+        self.fps_probe
+    }
+
+    /// Whether to replace a file that already exists at the output path.
+    pub fn overwrite(&self) -> bool {
+        self.overwrite
+    }
+
+    /// Whether the capture was started from the command line, so that it can
+    /// be controlled from the terminal.
+    pub fn interactive(&self) -> bool {
+        self.interactive
+    }
+
+    /// Whether reports should be printed as JSON.
+    pub fn json(&self) -> bool {
+        self.json
+    }
+
     fn args<'a, 'b>() -> App<'a, 'b> {
+        let duration_validator = |value: String| parse_duration(&value).map(|_| ());
+
         let u64_validator = |value: String| {
             u64::from_str(&value)
                 .map_err(|_| format!("{:?} is not an integer", value))
                 .map(|_| ())
         };
 
+        let framerate_validator = |value: String| match u64::from_str(&value) {
+            Ok(rate) if (1..=MAX_FRAMERATE).contains(&rate) => Ok(()),
+            _ => Err(format!(
+                "{:?} is not a framerate from 1 to {}",
+                value, MAX_FRAMERATE
+            )),
+        };
+
         let region = Arg::with_name("region")
             .short("r")
             .takes_value(true)
             .help("The region to capture")
-            .possible_values(&["screen", "window", "select"])
+            .long_help(
+                "The region to capture. 'active-output' captures the monitor with the \
+                 focused window, or the pointer if no window is focused. 'all-screens' \
+                 captures the whole desktop across every monitor, as the size of the \
+                 root window. Regions to record are selected with slop.",
+            )
+            .possible_values(&["screen", "window", "select", "active-output", "all-screens"])
             .default_value("screen");
 
         let mode = Arg::with_name("mode")
             .short("m")
             .takes_value(true)
-            .help("Whether to capture an image or video")
-            .possible_values(&["image", "video"])
+            .help("Whether to capture an image, video, animated GIF or audio")
+            .long_help(
+                "Whether to capture an image, video, animated GIF or audio. Video \
+                 recorded from a terminal can be paused by typing p and Enter, and \
+                 resumed with r and Enter. GIFs are recorded at the framerate given with -R for the --duration, or until \
+                 Enter is pressed. Audio is recorded without video from what is \
+                 playing, or from the --audio-source, for the --duration or until \
+                 Enter is pressed.",
+            )
+            .possible_values(&["image", "video", "gif", "audio"])
             .default_value("image");
 
         let framerate = Arg::with_name("rate")
             .short("R")
             .takes_value(true)
-            .help("Framerate (fps) when capturing video")
-            .validator(u64_validator)
+            .help("Framerate (fps) when capturing video or GIFs, up to 240")
+            .validator(framerate_validator)
             .default_value("30");
 
-        app_from_crate!().arg(region).arg(mode).arg(framerate)
-    }
-}
+        let rclone_remote = Arg::with_name("rclone-remote")
+            .long("rclone-remote")
+            .takes_value(true)
+            .value_name("REMOTE:PATH")
+            .help("Copy the capture to an rclone remote after saving");
 
-/// Possible regions of the screen.
-#[derive(Debug, Clone, Copy)]
-pub enum ScreenRegion {
-    Screen,
-    Window,
-    Select,
+        let rclone_link = Arg::with_name("rclone-link")
+            .long("rclone-link")
+            .requires("rclone-remote")
+            .help("Print a public link to the capture copied with rclone");
+
+        let input_sync = Arg::with_name("input-sync")
+            .long("input-sync")
+            .takes_value(true)
+            .help("Timing source for captured video frames")
+            .long_help(
+                "Timing source for captured video frames.\n\n\
+                 'wallclock' stamps each frame with the time it was read, keeping \
+                 video aligned with audio at the cost of some jitter when the grab \
+                 stalls. 'monotonic' keeps the evenly paced timestamps of the capture \
+                 device, giving smoother playback but allowing audio to drift over \
+                 long recordings.",
+            )
+            .possible_values(&["wallclock", "monotonic"]);
+
+        let pointer_at = Arg::with_name("pointer-at")
+            .long("pointer-at")
+            .takes_value(true)
+            .value_name("X,Y")
+            .help("Draw the pointer at a position in the captured image")
+            .validator(|value| parse_point(&value).map(|_| ()));
+
+        let probe_only = Arg::with_name("probe-only")
+            .long("probe-only")
+            .help("Report the region, codecs and output that would be used without capturing");
+
+        let startup_check = Arg::with_name("startup-check")
+            .long("startup-check")
+            .help("Check the tools the capture needs are installed before capturing")
+            .long_help(
+                "Check the tools the capture needs are installed before capturing, \
+                 such as ffmpeg, a screenshot tool, xdpyinfo, xwininfo and xprop, and \
+                 that a PulseAudio server is running when recording audio. Everything \
+                 missing is reported at once rather than failing part way through.",
+            );
+
+        let print_region = Arg::with_name("print-region")
+            .long("print-region")
+            .conflicts_with("probe-only")
+            .help("Print the region that would be captured as JSON without capturing")
+            .long_help(
+                "Print the region that would be captured as JSON without capturing, \
+                 such as {\"width\":1920,\"height\":1080,\"x\":0,\"y\":0}. The \
+                 window, active output or selection is found as it would be for a \
+                 capture. Only X11 regions can be printed.",
+            );
+
+        let json = Arg::with_name("json")
+            .long("json")
+            .requires("probe-only")
+            .help("Print reports as JSON");
+
+        let cursor_fallback = Arg::with_name("cursor-fallback")
+            .long("cursor-fallback")
+            .help("Track the cursor with xdotool and draw it onto the video afterwards")
+            .long_help(
+                "Track the cursor with xdotool and draw it onto the video afterwards.\n\n\
+                 Use this when the cursor is missing or flickers in recordings, which \
+                 happens with x11grab on drivers that use a hardware cursor. The video \
+                 is encoded a second time once recording stops, and the drawn cursor \
+                 is always the default arrow.",
+            );
+
+        let stop_after_idle = Arg::with_name("stop-after-idle")
+            .long("stop-after-idle")
+            .takes_value(true)
+            .value_name("DURATION")
+            .help("Stop recording video after no keyboard or mouse input for a time")
+            .validator(duration_validator);
+
+        let concat_screens = Arg::with_name("concat-screens")
+            .long("concat-screens")
+            .takes_value(true)
+            .min_values(0)
+            .use_delimiter(true)
+            .value_name("MONITORS")
+            .help("Record monitors side by side in one video")
+            .long_help(
+                "Record monitors side by side in one video. Takes a comma separated \
+                 list of monitor indices or output names as listed by \
+                 'xrandr --listmonitors', or \
+                 records every monitor if none are given. The region is ignored.\n\n\
+                 Each monitor is grabbed separately and tiled as it is encoded, so \
+                 this costs noticeably more CPU than recording the whole screen.",
+            );
+
+        let retry_encoder = Arg::with_name("retry-encoder")
+            .long("retry-encoder")
+            .overrides_with("no-retry-encoder")
+            .help("Fall back to the next video encoder if one fails to start (default)")
+            .long_help(
+                "Fall back to the next video encoder if one fails to start (default). \
+                 Hardware encoders such as h264_nvenc can be listed by ffmpeg but fail \
+                 when recording starts, in which case recording is started again with \
+                 the next encoder, such as libx264. Encoders chosen with --vcodec have \
+                 nothing to fall back to.",
+            );
+
+        let no_retry_encoder = Arg::with_name("no-retry-encoder")
+            .long("no-retry-encoder")
+            .overrides_with("retry-encoder")
+            .help("Give up if the video encoder fails to start");
+
+        let name = Arg::with_name("name")
+            .long("name")
+            .takes_value(true)
+            .value_name("TEMPLATE")
+            .help("Template used to name captures")
+            .long_help(
+                "Template used to name captures, to which the extension is added. \
+                 Placeholders are replaced with:\n\n\
+                 {host}        the host name up to the first dot\n\
+                 {date}        the current date and time, as given by --timestamp-format\n\
+                 {clipboard}   the first line of the clipboard, read with xclip\n\
+                 {selection}   the first line of the primary selection, read with xclip\n\
+                 {git-branch}  the branch of the git repository in the current directory\n\n\
+                 The clipboard, selection and branch are replaced with nothing if xclip or \
+                 git are missing or there is no value, and characters other than \
+                 letters, digits, '-', '_' and '.' are replaced with '-'.",
+            )
+            .default_value(DEFAULT_TEMPLATE)
+            .validator(|value| validate_template(&value));
+
+        let timestamp_format = Arg::with_name("timestamp-format")
+            .long("timestamp-format")
+            .takes_value(true)
+            .value_name("FORMAT")
+            .help("The chrono format {date} is replaced with in templates")
+            .long_help(
+                "The chrono format {date} is replaced with in the --name and \
+                 --watermark-text templates, such as %Y-%m-%dT%H%M%S for ISO 8601 \
+                 without the colons file managers dislike. The format must not \
+                 give dates containing '/'.",
+            )
+            .default_value(DEFAULT_DATE_FORMAT)
+            .validator(|value| validate_date_format(&value));
+
+        let adaptive_quality = Arg::with_name("adaptive-quality")
+            .long("adaptive-quality")
+            .help("Choose the video CRF and preset from the capture size and framerate")
+            .long_help(
+                "Choose the video CRF and preset from the capture size and framerate, \
+                 rather than always using CRF 16 with the fast preset.\n\n\
+                 Pixels           CRF  Preset\n\
+                 up to 1280x720   16   fast\n\
+                 up to 1920x1080  18   fast\n\
+                 up to 2560x1440  20   faster\n\
+                 larger           22   veryfast\n\n\
+                 Above 30 fps the next faster preset is used.",
+            );
+
+        let native = Arg::with_name("native")
+            .long("native")
+            .help("Capture images directly from the X11 server instead of gnome-screenshot");
+
+        let duration_from_selection = Arg::with_name("duration-from-selection")
+            .long("duration-from-selection")
+            .help("Start and stop recording video by pressing Enter");
+
+        let output = Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .value_name("PATH")
+            .help("Save the capture to a path instead of a generated name")
+            .long_help(
+                "Save the capture to a path instead of a generated name. A file that \
+                 already exists is only replaced with --overwrite. For video the \
+                 container is taken from the extension unless given with --format: .mkv, \
+                 .mp4, .mov or .webm.\n\n\
+                 With - video is written to standard output to be piped elsewhere, in \
+                 the container given with --format or matroska. Messages are written to \
+                 standard error instead.",
+            );
+
+        let format = Arg::with_name("format")
+            .long("format")
+            .visible_aliases(&["image-format", "container"])
+            .takes_value(true)
+            .help("The format to save images in, or the container for video")
+            .long_help(
+                "The format to save images in: png, jpg, webp or ppm. Without this the \
+                 format is taken from the extension of the output path, or is PNG. \
+                 Formats the screenshot tool can't save are converted from PNG with \
+                 ffmpeg, as are edited images.\n\n\
+                 For video this is the container instead: matroska, mp4, mov or webm, \
+                 named by format or extension. Without this the container is taken \
+                 from the extension of the output path, or is Matroska, and saved \
+                 recordings are named with its extension.",
+            );
+
+        let indicator = Arg::with_name("indicator")
+            .long("indicator")
+            .help("Show an indicator while recording video, using yad or zenity");
+
+        let offset_correct = Arg::with_name("offset-correct")
+            .long("offset-correct")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("auto|DX,DY")
+            .help("Correct the position of the capture region")
+            .long_help(
+                "Correct the position of the capture region for compositors where it \
+                 does not line up with what is on screen. 'auto' extends window \
+                 captures to include the frame the window manager draws around them, \
+                 as given by _NET_FRAME_EXTENTS. DX,DY moves the region by the given \
+                 number of pixels. The corrected region must be on the screen.",
+            )
+            .validator(|value| value.parse::<OffsetCorrection>().map(|_| ()));
+
+        let offset = Arg::with_name("offset")
+            .long("offset")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("+X+Y")
+            .help("Move the x11grab input by a number of pixels")
+            .long_help(
+                "Move the x11grab input by a number of pixels once the region is \
+                 found, such as +0+12 or -8+0, for displays that are shifted by \
+                 overscan. Unlike --offset-correct, the region may be moved partly \
+                 off the screen.",
+            )
+            .validator(|value| parse_offset(&value).map(|_| ()));
+
+        let benchmark_encoders = Arg::with_name("benchmark-encoders")
+            .long("benchmark-encoders")
+            .help("Compare the speed and output size of the available video encoders")
+            .long_help(
+                "Compare the speed and output size of the available video encoders. \
+                 A short test clip the size of the capture region is encoded with \
+                 each H.264 encoder ffmpeg supports, and the encoders are listed from \
+                 fastest to slowest. Nothing is captured.",
+            );
+
+        let exclude_struts = Arg::with_name("exclude-struts")
+            .long("exclude-struts")
+            .help("Leave panels and docks out of screen captures")
+            .long_help(
+                "Leave panels and docks out of screen captures, capturing only the work \
+                 area given by _NET_WORKAREA.",
+            );
+
+        let record_stdin_commands = Arg::with_name("record-stdin-commands")
+            .long("record-stdin-commands")
+            .help("Control video recording with commands from standard input")
+            .long_help(
+                "Control video recording with commands from standard input, one per \
+                 line. Each command is answered with a line starting with 'ok' or \
+                 'error'.\n\n\
+                 start          start recording\n\
+                 pause, p       pause recording\n\
+                 resume, r      resume a paused recording\n\
+                 marker [NAME]  start a chapter at the current time\n\
+                 snapshot       save the screen as an image next to the recording\n\
+                 stop           stop and save the recording\n\n\
+                 The end of input stops the recording. Each time recording is \
+                 paused and resumed a new segment is started, and the segments are \
+                 joined into a single recording when it stops.",
+            );
+
+        let tonemap = Arg::with_name("tonemap")
+            .long("tonemap")
+            .help("Tone map HDR video to SDR")
+            .long_help(
+                "Tone map HDR video to SDR so that it looks right on ordinary displays. \
+                 This needs ffmpeg built with zimg. HDR metadata is not passed through, \
+                 and as x11grab captures 8 bits per channel the result can only be as \
+                 good as what the X server provides.",
+            );
+
+        let split_av = Arg::with_name("split-av")
+            .long("split-av")
+            .help("Write audio to a separate file from the video")
+            .long_help(
+                "Write audio to a separate file next to the video, as .opus or .m4a \
+                 depending on the available encoders. Both are recorded in the same pass.",
+            );
+
+        let monitor = Arg::with_name("monitor")
+            .long("monitor")
+            .takes_value(true)
+            .value_name("MONITOR")
+            .help("Capture a single monitor")
+            .long_help(
+                "Capture a single monitor instead of the whole screen. Takes a monitor \
+                 index or an output name such as HDMI-1, as listed by \
+                 'xrandr --listmonitors'. Output names stay the same across reboots \
+                 and hotplugging where indices may not. On Wayland only output names, \
+                 as listed by 'wlr-randr', can be used.",
+            );
+
+        let audio_delay = Arg::with_name("audio-delay")
+            .long("audio-delay")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("MILLISECONDS")
+            .help("Shift the audio to correct its sync with the video")
+            .long_help(
+                "Shift the audio by a number of milliseconds to correct its sync with \
+                 the video. Positive values delay the audio for when it is heard too \
+                 early, negative values advance it for when it is heard too late. \
+                 Limited to 10 seconds either way.",
+            )
+            .validator(|value| match i64::from_str(&value) {
+                Ok(delay) if delay.abs() <= MAX_AUDIO_DELAY => Ok(()),
+                Ok(_) => Err(format!("{} is more than 10 seconds", value)),
+                Err(_) => Err(format!("{:?} is not an integer", value)),
+            });
+
+        let ffmpeg_path = Arg::with_name("ffmpeg-path")
+            .long("ffmpeg-path")
+            .takes_value(true)
+            .value_name("PATH")
+            .env("SCREENCAP_FFMPEG")
+            .help("The ffmpeg binary to use")
+            .long_help(
+                "The ffmpeg binary to use instead of the one found on the PATH, for \
+                 when the system ffmpeg is too old or lacks an encoder. It is used for \
+                 recording, converting and checking what ffmpeg supports.",
+            )
+            .validator(|value| {
+                if is_executable(Path::new(&value)) {
+                    Ok(())
+                } else {
+                    Err(format!("{:?} is not an executable file", value))
+                }
+            });
+
+        let check_compat = Arg::with_name("check-compat")
+            .long("check-compat")
+            .takes_value(true)
+            .number_of_values(3)
+            .value_names(&["CONTAINER", "VIDEO", "AUDIO"])
+            .help("Check that a container can hold the given video and audio codecs")
+            .long_help(
+                "Check that a container can hold the given video and audio codecs, \
+                 such as 'mp4 libx264 aac', and suggest alternatives if it can't. \
+                 Codecs can be given by ffmpeg encoder name. Nothing is captured.",
+            );
+
+        let watch_speed = Arg::with_name("watch-speed")
+            .long("watch-speed")
+            .help("Warn when encoding can't keep up with recording")
+            .long_help(
+                "Warn when encoding stays slower than real time for more than ten \
+                 seconds, as ffmpeg then drops frames without saying so. The warning \
+                 suggests a framerate that the encoder could keep up with.",
+            );
+
+        let progress = Arg::with_name("progress")
+            .long("progress")
+            .takes_value(true)
+            .possible_values(&["json"])
+            .help("Report recording progress to standard error")
+            .long_help(
+                "Report recording progress to standard error for programs wrapping \
+                 screencap. With 'json' each update is a JSON object on its own line \
+                 with the keys 'elapsed' (seconds recorded), 'frames' (frames encoded), \
+                 'fps' (frames encoded per second), 'drops' (frames dropped), 'speed' \
+                 (encoding speed relative to real time, or null) and 'done' (whether \
+                 it is the last update).",
+            );
+
+        let safe_mode = Arg::with_name("safe-mode")
+            .long("safe-mode")
+            .help("Only use ffmpeg and the X11 server for capturing")
+            .long_help(
+                "Only use ffmpeg and the X11 server for capturing, for minimal systems \
+                 or to rule out problems with other tools. Images are grabbed as a \
+                 single frame with ffmpeg instead of with gnome-screenshot, and the \
+                 screen size is read from the X11 server instead of xdpyinfo.\n\n\
+                 Only the whole screen can be captured, HDR displays are not detected, \
+                 and options that need other tools can't be used: --monitor, \
+                 --concat-screens, --exclude-struts (xprop), --indicator (yad or \
+                 zenity), --rclone-remote (rclone), --stop-after-idle (xprintidle), \
+                 --cursor-fallback (xdotool), and the {clipboard}, {selection} and \
+                 {git-branch} name placeholders (xclip and git).",
+            );
+
+        let duration = Arg::with_name("duration")
+            .long("duration")
+            .takes_value(true)
+            .value_name("DURATION")
+            .help("Stop recording video after a time")
+            .long_help(
+                "Stop recording video after a time, given in seconds (90), with units \
+                 (1m30s, 2h) or as HH:MM:SS (00:01:30).",
+            )
+            .validator(duration_validator);
+
+        let include_shadow = Arg::with_name("include-shadow")
+            .long("include-shadow")
+            .overrides_with("no-shadow")
+            .help("Include the shadow drawn by a window in window captures (default)");
+
+        let no_shadow = Arg::with_name("no-shadow")
+            .long("no-shadow")
+            .overrides_with("include-shadow")
+            .help("Leave the shadow drawn by a window out of window captures")
+            .long_help(
+                "Leave the shadow drawn by a window out of window captures. Windows \
+                 with client side decorations, such as most GTK applications, draw \
+                 their shadow inside the window, which otherwise shows up as a \
+                 transparent or black border. The shadow is read from the window's \
+                 _GTK_FRAME_EXTENTS property.",
+            );
+
+        let lut = Arg::with_name("lut")
+            .long("lut")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Colour grade captures with a 3D LUT")
+            .long_help(
+                "Colour grade captures with a 3D LUT file using ffmpeg's lut3d filter. \
+                 Supported formats are Resolve and Iridas .cube, Nuke .3dl, Davinci \
+                 .dat, Pandora .m3d and Cinespace .csp.",
+            )
+            .validator(|value| {
+                let path = Path::new(&value);
+                let extension = path.extension().and_then(|extension| extension.to_str());
+                if !LUT_EXTENSIONS.contains(&extension.unwrap_or_default()) {
+                    Err(format!("{:?} is not a supported LUT file", value))
+                } else if !path.is_file() {
+                    Err(format!("LUT file {:?} does not exist", value))
+                } else {
+                    Ok(())
+                }
+            });
+
+        let scale = Arg::with_name("scale")
+            .long("scale")
+            .takes_value(true)
+            .value_name("W:H")
+            .help("Scale captures to a size, such as 1280:-1 to keep the aspect ratio")
+            .long_help(
+                "Scale captures to a size using ffmpeg's scale filter, such as 1280:720. \
+                 A side of -1 is chosen to keep the aspect ratio, and -2 does the same \
+                 but keeps it even as most video encoders need. Captures are edited \
+                 at full size and scaled last.",
+            )
+            .validator(|value| validate_scale(&value));
+
+        let repeat_last = Arg::with_name("repeat-last")
+            .long("repeat-last")
+            .help("Repeat the last capture")
+            .long_help(
+                "Repeat the last capture with the same arguments, capturing the same \
+                 area for window and active output captures even if another window \
+                 or monitor now has focus. Any other arguments are ignored.",
+            );
+
+        let watermark_text = Arg::with_name("watermark-text")
+            .long("watermark-text")
+            .takes_value(true)
+            .value_name("TEMPLATE")
+            .help("Mark captures with text")
+            .long_help(
+                "Mark captures with text, which can use the same placeholders as \
+                 --name such as {host} and {date}.",
+            )
+            .validator(|value| validate_template(&value));
+
+        let watermark_position = Arg::with_name("watermark-position")
+            .long("watermark-position")
+            .takes_value(true)
+            .help("Where to place the watermark")
+            .possible_values(&[
+                "top-left",
+                "top-right",
+                "bottom-left",
+                "bottom-right",
+                "center",
+            ])
+            .default_value("bottom-right");
+
+        let watermark_opacity = Arg::with_name("watermark-opacity")
+            .long("watermark-opacity")
+            .takes_value(true)
+            .help("Opacity of the watermark from 0 to 1")
+            .default_value("0.7")
+            .validator(|value| match f64::from_str(&value) {
+                Ok(opacity) if (0.0..=1.0).contains(&opacity) => Ok(()),
+                _ => Err(format!("{:?} is not an opacity from 0 to 1", value)),
+            });
+
+        let watermark_size = Arg::with_name("watermark-size")
+            .long("watermark-size")
+            .takes_value(true)
+            .help("Font size of the watermark in pixels")
+            .default_value("24")
+            .validator(u64_validator);
+
+        let watermark_font = Arg::with_name("watermark-font")
+            .long("watermark-font")
+            .takes_value(true)
+            .value_name("FILE")
+            .requires("watermark-text")
+            .help("Font file for the watermark, instead of fontconfig's default")
+            .validator(|value| {
+                if Path::new(&value).is_file() {
+                    Ok(())
+                } else {
+                    Err(format!("Font file {:?} does not exist", value))
+                }
+            });
+
+        let webcam = Arg::with_name("webcam")
+            .long("webcam")
+            .takes_value(true)
+            .value_name("DEVICE")
+            .help("Overlay a webcam, such as /dev/video0, in a corner of recordings")
+            .long_help(
+                "Overlay a webcam, such as /dev/video0, in a corner of recordings. The \
+                 webcam is recorded at its own size with ffmpeg's v4l2 input.",
+            );
+
+        let webcam_position = Arg::with_name("webcam-position")
+            .long("webcam-position")
+            .takes_value(true)
+            .help("The corner to overlay the webcam in")
+            .possible_values(&["top-left", "top-right", "bottom-left", "bottom-right"])
+            .default_value("bottom-right");
+
+        let input_buffer = Arg::with_name("input-buffer")
+            .long("input-buffer")
+            .takes_value(true)
+            .value_name("MB")
+            .help("Buffer captured frames to smooth over encoding hiccups")
+            .long_help(
+                "Buffer up to this many megabytes of captured frames for each video \
+                 input, so that frames grabbed while the encoder is briefly busy are \
+                 queued rather than dropped. Frames are buffered raw, so a 1920x1080 \
+                 frame takes about 8 MB and 256 MB buffers around one second at 30 \
+                 fps. This only smooths over short hiccups: if encoding is slower than \
+                 real time overall, see --watch-speed. Compare the 'drops' reported by \
+                 --progress json with and without a buffer to see the difference.",
+            )
+            .validator(u64_validator);
+
+        let contact_sheet = Arg::with_name("contact-sheet")
+            .long("contact-sheet")
+            .help("Make a contact sheet of frames from the recording")
+            .long_help(
+                "Make a contact sheet of evenly spaced frames from the recording once \
+                 it is finished, saved as a PNG next to it.",
+            );
+
+        let contact_sheet_grid = Arg::with_name("contact-sheet-grid")
+            .long("contact-sheet-grid")
+            .takes_value(true)
+            .value_name("COLUMNSxROWS")
+            .help("The number of frames across and down the contact sheet")
+            .default_value("4x4")
+            .validator(|value| parse_grid(&value).map(|_| ()));
+
+        let contact_sheet_width = Arg::with_name("contact-sheet-width")
+            .long("contact-sheet-width")
+            .takes_value(true)
+            .value_name("PIXELS")
+            .help("The width of each frame on the contact sheet")
+            .default_value("320")
+            .validator(u64_validator);
+
+        let list_codecs = Arg::with_name("list-codecs")
+            .long("list-codecs")
+            .help("List the formats and codecs that would be used to record video")
+            .long_help(
+                "List the formats and codecs that would be used to record video. The \
+                 container format, x11 and pulse inputs and audio and video encoders \
+                 are chosen from what ffmpeg supports in the same order as when \
+                 recording. Nothing is captured.",
+            );
+
+        let vcodec = Arg::with_name("vcodec")
+            .long("vcodec")
+            .takes_value(true)
+            .value_name("ENCODER")
+            .help("The ffmpeg video encoder to record with")
+            .long_help(
+                "The ffmpeg video encoder to record with, such as libx264. By default \
+                 the first of h264_nvenc, h264_qsv, libx264 and h264 that ffmpeg \
+                 supports is used. A chosen encoder is never fallen back from.",
+            );
+
+        let no_audio = Arg::with_name("no-audio")
+            .long("no-audio")
+            .help("Record video without audio")
+            .long_help(
+                "Record video without audio. Nothing is recorded from PulseAudio, so \
+                 video can be recorded on systems without a PulseAudio server.",
+            );
+
+        let audio_source = Arg::with_name("audio-source")
+            .long("audio-source")
+            .takes_value(true)
+            .value_name("SOURCE")
+            .help("The PulseAudio source to record audio from")
+            .long_help(
+                "The PulseAudio source to record audio from instead of the default \
+                 source, such as the monitor of an output to record what is playing. \
+                 Use --list-audio-sources to see the available sources.",
+            );
+
+        let list_audio_sources = Arg::with_name("list-audio-sources")
+            .long("list-audio-sources")
+            .help("List the PulseAudio sources that can be recorded from")
+            .long_help(
+                "List the PulseAudio sources that can be recorded from with \
+                 --audio-source, as given by 'pactl list sources short'. Nothing is \
+                 captured.",
+            );
+
+        let mix_audio = Arg::with_name("mix-audio")
+            .long("mix-audio")
+            .help("Mix what is playing into the recorded audio")
+            .long_help(
+                "Mix what is playing into the recorded audio. The monitor of the \
+                 default output is recorded along with the audio source and the two \
+                 are mixed into a single audio track.",
+            );
+
+        let desktop_audio = Arg::with_name("desktop-audio")
+            .long("desktop-audio")
+            .conflicts_with_all(&["audio-source", "mix-audio"])
+            .help("Record what is playing instead of the microphone")
+            .long_help(
+                "Record what is playing instead of the microphone. Audio is recorded \
+                 from the monitor of the default sink, as given by \
+                 'pactl get-default-sink'.",
+            );
+
+        let crf = Arg::with_name("crf")
+            .long("crf")
+            .takes_value(true)
+            .value_name("CRF")
+            .help("The constant rate factor to encode video with, from 0 to 51 [default: 16]")
+            .long_help(
+                "The constant rate factor to encode video with, from 0 for lossless \
+                 to 51 for the smallest files. Higher values give smaller files of \
+                 lower quality. The default is 16, or chosen from the capture size \
+                 with --adaptive-quality. Hardware encoders such as h264_nvenc and \
+                 h264_qsv ignore the CRF, though it is still passed to them.",
+            )
+            .validator(|value| match u32::from_str(&value) {
+                Ok(crf) if crf <= 51 => Ok(()),
+                _ => Err(format!("{:?} is not a CRF from 0 to 51", value)),
+            });
+
+        let bitrate = Arg::with_name("bitrate")
+            .long("bitrate")
+            .takes_value(true)
+            .value_name("RATE")
+            .conflicts_with("crf")
+            .help("Encode video at a bitrate, such as 4M or 800k, instead of a CRF")
+            .long_help(
+                "Encode video at a bitrate in bits per second, such as 4M or 800k, \
+                 instead of a constant rate factor. File sizes are then predictable \
+                 rather than the quality. The bitrate is also the maximum, averaged \
+                 over two seconds of video.",
+            )
+            .validator(|value| parse_bitrate(&value).map(|_| ()));
+
+        let preset = Arg::with_name("preset")
+            .long("preset")
+            .takes_value(true)
+            .value_name("PRESET")
+            .help("The encoder preset to encode video with [default: fast]")
+            .long_help(
+                "The encoder preset to encode video with, such as veryfast or slow \
+                 for libx264. Slower presets give smaller files for the same quality \
+                 but may not keep up with recording. The default is fast, or chosen \
+                 from the capture size with --adaptive-quality.",
+            );
+
+        let clipboard = Arg::with_name("clipboard")
+            .long("clipboard")
+            .help("Copy the capture to the clipboard with xclip")
+            .long_help(
+                "Copy the capture to the clipboard with xclip. Images and GIFs are \
+                 copied as images so they can be pasted into other programs, and for \
+                 video the path of the recording is copied.",
+            );
+
+        let notify = Arg::with_name("notify")
+            .long("notify")
+            .help("Send a desktop notification once the capture is saved")
+            .long_help(
+                "Send a desktop notification with notify-send once the capture is \
+                 saved, which for video is when recording stops. Notifications for \
+                 images show the image as their icon.",
+            );
+
+        let delay = Arg::with_name("delay")
+            .long("delay")
+            .takes_value(true)
+            .value_name("DURATION")
+            .help("Count down for a time before capturing")
+            .long_help(
+                "Count down for a time before capturing, such as 5 or 1m, to give \
+                 time to set up what is being captured. The window or output to \
+                 capture is found once the countdown ends.",
+            )
+            .default_value("0")
+            .validator(duration_validator);
+
+        let repeat = Arg::with_name("repeat")
+            .long("repeat")
+            .takes_value(true)
+            .value_name("COUNT")
+            .help("Capture a number of images one after another")
+            .long_help(
+                "Capture a number of images one after another, waiting for the \
+                 --interval between each. Each image is named from the --name \
+                 template when it is captured, or numbered after the --output, and \
+                 all of them are listed once they are saved.",
+            )
+            .validator(|value| match u64::from_str(&value) {
+                Ok(count) if count > 0 => Ok(()),
+                _ => Err(format!("{:?} is not a positive number of captures", value)),
+            });
+
+        let interval = Arg::with_name("interval")
+            .long("interval")
+            .takes_value(true)
+            .value_name("DURATION")
+            .requires("repeat")
+            .help("Time to wait between repeated captures (default 1 second)")
+            .validator(duration_validator);
+
+        let write_config = Arg::with_name("write-config")
+            .long("write-config")
+            .help("Write a default config file")
+            .long_help(
+                "Write a default config file to ~/.config/screencap/config.toml, or \
+                 under $XDG_CONFIG_HOME if it is set. Settings in the config file are \
+                 used unless they are given on the command line or in an env file, \
+                 with the same keys as env files in lower case, such as \
+                 audio-source = \"default\". Nothing is captured.",
+            );
+
+        let region_geometry = Arg::with_name("region-geometry")
+            .long("region-geometry")
+            .takes_value(true)
+            .value_name("WxH+X+Y")
+            .help("Capture an exact region of the screen, such as 1280x720+100+50")
+            .validator(|value| parse_region_geometry(&value).map(|_| ()));
+
+        let screenshot_tool = Arg::with_name("screenshot-tool")
+            .long("screenshot-tool")
+            .takes_value(true)
+            .value_name("TOOL")
+            .help("The tool to take screenshots with on X11")
+            .long_help(
+                "The tool to take screenshots with on X11. Without this the first of \
+                 gnome-screenshot, scrot, maim and import that is installed is used.",
+            )
+            .possible_values(&["gnome-screenshot", "scrot", "maim", "import"]);
+
+        let log = Arg::with_name("log")
+            .long("log")
+            .takes_value(true)
+            .value_name("PATH")
+            .help("Write ffmpeg's output while recording video to a file")
+            .long_help(
+                "Write ffmpeg's output while recording video to a file, which helps \
+                 to diagnose recordings that fail or come out black. Without this the \
+                 output is discarded unless --verbose is given.",
+            );
+
+        let verbose = Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .multiple(true)
+            .help("Report what is being done, and more with -vv")
+            .long_help(
+                "Report what is being done to standard error, and with -vv the codecs \
+                 and inputs chosen. ffmpeg's output while recording video is also \
+                 written to ~/.cache/screencap/last.log, or to the path given to --log.",
+            );
+
+        let quiet = Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .conflicts_with("verbose")
+            .help("Report nothing but errors")
+            .long_help(
+                "Report nothing but errors, leaving out progress, warnings and where \
+                 the capture was saved. What was asked for is still printed, such as \
+                 with --print-path, --list-codecs or --dry-run.",
+            );
+
+        let dry_run = Arg::with_name("dry-run")
+            .long("dry-run")
+            .conflicts_with_all(&["native", "safe-mode"])
+            .help("Print the command that would capture instead of running it")
+            .long_help(
+                "Print the ffmpeg or screenshot command that would capture, quoted to \
+                 be pasted into a shell, instead of running it. Only X11 image and \
+                 video capture are supported.",
+            );
+
+        let limit_framerate = Arg::with_name("limit-framerate")
+            .long("limit-framerate")
+            .help("Record video no faster than the refresh rate of the display")
+            .long_help(
+                "Record video no faster than the refresh rate of the primary display, \
+                 as read from xrandr. Without this a framerate above the refresh rate \
+                 only gives a warning.",
+            );
+
+        let cursor = Arg::with_name("cursor")
+            .long("cursor")
+            .overrides_with("no-cursor")
+            .help("Draw the cursor in video and GIFs (default), and in images")
+            .long_help(
+                "Draw the cursor in video and GIFs, which is the default, and in \
+                 images, which otherwise leave it out. import can't capture the \
+                 cursor in images.",
+            );
+
+        let no_cursor = Arg::with_name("no-cursor")
+            .long("no-cursor")
+            .overrides_with("cursor")
+            .help("Leave the cursor out of captures");
+
+        let show_region = Arg::with_name("show-region")
+            .long("show-region")
+            .overrides_with("no-show-region")
+            .help("Draw a border around the region while recording video (default)");
+
+        let no_show_region = Arg::with_name("no-show-region")
+            .long("no-show-region")
+            .overrides_with("show-region")
+            .help("Don't draw a border around the region while recording video")
+            .long_help(
+                "Don't draw a border around the region while recording video. The \
+                 border is of little use when recording the whole screen, and can \
+                 flicker into recordings with some compositors.",
+            );
+
+        let refresh_caps = Arg::with_name("refresh-caps")
+            .long("refresh-caps")
+            .help("Probe what ffmpeg supports again instead of using the cache")
+            .long_help(
+                "Probe what ffmpeg supports again instead of using the cache. The \
+                 formats and encoders ffmpeg lists are cached in \
+                 ~/.cache/screencap/capabilities.json until ffmpeg changes.",
+            );
+
+        let print_path = Arg::with_name("print-path")
+            .long("print-path")
+            .conflicts_with("dry-run")
+            .help("Print only the path of the capture to standard output")
+            .long_help(
+                "Print only the path of the capture to standard output, so that \
+                 scripts can use it as FILE=$(screencap --print-path). Every other \
+                 message is written to standard error.",
+            );
+
+        let upload = Arg::with_name("upload")
+            .long("upload")
+            .takes_value(true)
+            .value_name("COMMAND")
+            .help("Run a shell command to upload the capture after saving")
+            .long_help(
+                "Run a shell command to upload the capture after saving, such as \
+                 'scp {} host:shots/'. Each {} is replaced by the path of the capture, \
+                 which is otherwise added to the end. What the command prints, such \
+                 as a link, is printed.",
+            );
+
+        let window_name = Arg::with_name("window-name")
+            .long("window-name")
+            .takes_value(true)
+            .value_name("NAME")
+            .help("Capture the window with a title or class instead of the focused one")
+            .long_help(
+                "Capture the window with a title or class instead of the focused one, \
+                 found with xdotool. The name is a regular expression matched against \
+                 the titles of visible windows, then their classes. The window is \
+                 captured from the screen, so anything covering it is captured too.",
+            );
+
+        let fps_probe = Arg::with_name("fps-probe")
+            .long("fps-probe")
+            .help("Measure the framerate that can be recorded without capturing")
+            .long_help(
+                "Measure the framerate that can be recorded without capturing. The \
+                 capture region is recorded for a few seconds with the video encoder \
+                 and the output discarded, then the average framerate achieved is \
+                 compared with the one requested. Nothing is saved.",
+            );
+
+        let overwrite = Arg::with_name("overwrite")
+            .long("overwrite")
+            .overrides_with("no-clobber")
+            .help("Replace a file that already exists at the output path")
+            .long_help(
+                "Replace a file that already exists at the output path. Without this \
+                 nothing is captured when the output path exists.",
+            );
+
+        let no_clobber = Arg::with_name("no-clobber")
+            .long("no-clobber")
+            .overrides_with("overwrite")
+            .help("Refuse to replace a file at the output path (default)");
+
+        let env_file = Arg::with_name("env-file")
+            .long("env-file")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Load settings from a file of KEY=VALUE lines")
+            .long_help(
+                "Load settings from a file of KEY=VALUE lines. Keys are the names of \
+                 options in upper case with dashes replaced by underscores, such as \
+                 MODE=video or INPUT_SYNC=wallclock, and flags take true or false. \
+                 Options given on the command line take precedence over the file.",
+            );
+
+        app_from_crate!()
+            .arg(region)
+            .arg(mode)
+            .arg(framerate)
+            .arg(rclone_remote)
+            .arg(rclone_link)
+            .arg(input_sync)
+            .arg(pointer_at)
+            .arg(cursor_fallback)
+            .arg(stop_after_idle)
+            .arg(concat_screens)
+            .arg(retry_encoder)
+            .arg(no_retry_encoder)
+            .arg(name)
+            .arg(timestamp_format)
+            .arg(adaptive_quality)
+            .arg(native)
+            .arg(duration_from_selection)
+            .arg(output)
+            .arg(format)
+            .arg(indicator)
+            .arg(offset_correct)
+            .arg(offset)
+            .arg(benchmark_encoders)
+            .arg(exclude_struts)
+            .arg(record_stdin_commands)
+            .arg(tonemap)
+            .arg(split_av)
+            .arg(monitor)
+            .arg(audio_delay)
+            .arg(ffmpeg_path)
+            .arg(check_compat)
+            .arg(watch_speed)
+            .arg(progress)
+            .arg(safe_mode)
+            .arg(duration)
+            .arg(include_shadow)
+            .arg(no_shadow)
+            .arg(lut)
+            .arg(scale)
+            .arg(repeat_last)
+            .arg(watermark_text)
+            .arg(watermark_position)
+            .arg(watermark_opacity)
+            .arg(watermark_size)
+            .arg(watermark_font)
+            .arg(webcam)
+            .arg(webcam_position)
+            .arg(input_buffer)
+            .arg(contact_sheet)
+            .arg(contact_sheet_grid)
+            .arg(contact_sheet_width)
+            .arg(list_codecs)
+            .arg(vcodec)
+            .arg(no_audio)
+            .arg(audio_source)
+            .arg(list_audio_sources)
+            .arg(mix_audio)
+            .arg(desktop_audio)
+            .arg(crf)
+            .arg(bitrate)
+            .arg(preset)
+            .arg(clipboard)
+            .arg(notify)
+            .arg(delay)
+            .arg(repeat)
+            .arg(interval)
+            .arg(write_config)
+            .arg(region_geometry)
+            .arg(screenshot_tool)
+            .arg(log)
+            .arg(verbose)
+            .arg(quiet)
+            .arg(dry_run)
+            .arg(limit_framerate)
+            .arg(cursor)
+            .arg(no_cursor)
+            .arg(show_region)
+            .arg(no_show_region)
+            .arg(refresh_caps)
+            .arg(print_path)
+            .arg(upload)
+            .arg(window_name)
+            .arg(fps_probe)
+            .arg(overwrite)
+            .arg(no_clobber)
+            .arg(probe_only)
+            .arg(startup_check)
+            .arg(print_region)
+            .arg(json)
+            .arg(env_file)
+    }
+}
+
+/// Read an env file as the command line arguments for each of its settings.
+///
+/// Settings for arguments that are already on the command line are skipped.
+fn env_file_args(path: &str, matches: &ArgMatches) -> Result<Vec<Vec<OsString>>, clap::Error> {
+    let contents = read_to_string(path).map_err(|e| {
+        clap::Error::with_description(
+            &format!("Could not read env file {:?}: {}", path, e),
+            ErrorKind::Io,
+        )
+    })?;
+
+    let mut args = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = |message: &str| {
+            clap::Error::with_description(
+                &format!("{}:{}: {}", path, number + 1, message),
+                ErrorKind::InvalidValue,
+            )
+        };
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid("expected KEY=VALUE"))?;
+        let (key, value) = (key.trim(), unquote(value.trim()));
+
+        args.push(setting_args(key, value, matches).map_err(|message| invalid(&message))?);
+    }
+
+    Ok(args)
+}
+
+/// Get the command line arguments for a setting from a file, which are none
+/// if it was already given.
+fn setting_args(key: &str, value: &str, matches: &ArgMatches) -> Result<Vec<OsString>, String> {
+    let name = key.to_lowercase().replace('_', "-");
+    let (flag, takes_value) = setting(&name).ok_or_else(|| format!("unknown setting {:?}", key))?;
+    let mut args = Vec::new();
+    if matches.occurrences_of(&name) > 0 {
+        return Ok(args);
+    }
+
+    if takes_value {
+        args.push(flag.into());
+        if !value.is_empty() {
+            args.push(value.into());
+        }
+    } else {
+        match value {
+            "1" | "true" | "yes" => args.push(flag.into()),
+            "0" | "false" | "no" => {}
+            _ => return Err(format!("{:?} is not true or false", value)),
+        }
+    }
+    Ok(args)
+}
+
+/// Put settings from a file before the command line arguments.
+///
+/// Settings that conflict with the arguments are left out so that what was
+/// given takes their place, as with `crf` in a file and `--bitrate` given.
+fn with_settings(settings: Vec<Vec<OsString>>, args: Vec<OsString>) -> Vec<OsString> {
+    let mut args = args.into_iter();
+    let mut settled: Vec<OsString> = args.next().into_iter().collect();
+    let given: Vec<OsString> = args.collect();
+
+    for setting in settings {
+        let trial: Vec<OsString> = settled
+            .iter()
+            .chain(&setting)
+            .chain(&given)
+            .cloned()
+            .collect();
+        let checked = Config::args()
+            .get_matches_from_safe(&trial)
+            .and_then(|matches| Config::from_settled(matches, trial, None));
+        // Any other error is reported once everything is in place.
+        match checked {
+            Err(error) if error.kind == ErrorKind::ArgumentConflict => {}
+            _ => settled.extend(setting),
+        }
+    }
+    settled.extend(given);
+    settled
+}
+
+/// Find an argument that can be set from an env file or the config file,
+/// getting the flag used to pass it on the command line and whether it takes
+/// a value.
+fn setting(name: &str) -> Option<(String, bool)> {
+    let &(_, takes_value) = SETTINGS.iter().find(|(setting, _)| *setting == name)?;
+    let flag = match SHORT_SETTINGS.iter().find(|(setting, _)| *setting == name) {
+        Some((_, short)) => format!("-{}", short),
+        None => format!("--{}", name),
+    };
+    Some((flag, takes_value))
+}
+
+/// Get the path of the config file.
+fn config_file() -> Option<PathBuf> {
+    let mut path = match var_os("XDG_CONFIG_HOME") {
+        Some(config) => PathBuf::from(config),
+        None => {
+            let mut home = PathBuf::from(var_os("HOME")?);
+            home.push(".config");
+            home
+        }
+    };
+    path.push("screencap/config.toml");
+    Some(path)
+}
+
+/// Write the default config file, which must not already exist.
+///
+/// Gets the path of the config file.
+pub fn write_default_config() -> io::Result<PathBuf> {
+    let path = config_file()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No HOME to write config in"))?;
+    if path.exists() {
+        let message = format!("Config file {:?} already exists", path);
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, message));
+    }
+    fs::create_dir_all(path.parent().expect("Config directory"))?;
+    fs::write(&path, DEFAULT_CONFIG)?;
+    Ok(path)
+}
+
+/// Read the config file as the command line arguments for each of its
+/// settings.
+///
+/// Only top level keys with string, integer and boolean values are read, which
+/// is all the settings need.
+fn config_file_args(path: &Path, matches: &ArgMatches) -> Result<Vec<Vec<OsString>>, clap::Error> {
+    let contents = read_to_string(path).map_err(|e| {
+        clap::Error::with_description(
+            &format!("Could not read config file {:?}: {}", path, e),
+            ErrorKind::Io,
+        )
+    })?;
+
+    let mut args = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = |message: &str| {
+            clap::Error::with_description(
+                &format!("{}:{}: {}", path.display(), number + 1, message),
+                ErrorKind::InvalidValue,
+            )
+        };
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid("expected key = value"))?;
+        let key = unquote(key.trim());
+        let value = toml_value(value.trim()).map_err(|message| invalid(&message))?;
+        args.push(setting_args(key, &value, matches).map_err(|message| invalid(&message))?);
+    }
+
+    Ok(args)
+}
+
+/// Read a TOML string, integer or boolean value, along with any comment after
+/// it.
+fn toml_value(value: &str) -> Result<String, String> {
+    let unsupported = || format!("{:?} is not a string, integer or boolean", value);
+
+    let (parsed, rest) = if let Some(literal) = value.strip_prefix('\'') {
+        let end = literal.find('\'').ok_or_else(unsupported)?;
+        (literal[..end].to_owned(), &literal[end + 1..])
+    } else if let Some(basic) = value.strip_prefix('"') {
+        let mut parsed = String::new();
+        let mut chars = basic.char_indices();
+        let end = loop {
+            match chars.next().ok_or_else(unsupported)? {
+                (end, '"') => break end,
+                (_, '\\') => match chars.next().ok_or_else(unsupported)?.1 {
+                    'n' => parsed.push('\n'),
+                    't' => parsed.push('\t'),
+                    c @ ('"' | '\\') => parsed.push(c),
+                    _ => return Err(unsupported()),
+                },
+                (_, c) => parsed.push(c),
+            }
+        };
+        (parsed, &basic[end + 1..])
+    } else {
+        let end = value.find(['#', ' ', '\t']).unwrap_or(value.len());
+        let (bare, rest) = value.split_at(end);
+        let integer = bare.strip_prefix('+').unwrap_or(bare).replace('_', "");
+        match bare {
+            "true" | "false" => (bare.to_owned(), rest),
+            _ if integer.parse::<i64>().is_ok() => (integer, rest),
+            _ => return Err(unsupported()),
+        }
+    };
+
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(parsed)
+    } else {
+        Err(unsupported())
+    }
+}
+
+/// Remove matching quotes from around a value.
+fn unquote(value: &str) -> &str {
+    for quote in &['"', '\''] {
+        if value.len() >= 2 && value.starts_with(*quote) && value.ends_with(*quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+/// Determine the image format from the format and output path given.
+///
+/// The extension of the output path must match the format if both are given.
+fn resolve_image_format(
+    format: Option<&str>,
+    output: Option<&Path>,
+) -> Result<ImageFormat, String> {
+    let format: Option<ImageFormat> = format
+        .map(|format| {
+            format
+                .parse()
+                .map_err(|_| format!("{:?} is not an image format", format))
+        })
+        .transpose()?;
+    let extension = output
+        .and_then(Path::extension)
+        .and_then(|extension| extension.to_str());
+    let from_extension = extension.and_then(|extension| extension.parse().ok());
+
+    match (format, from_extension) {
+        (Some(format), Some(from_extension)) if format != from_extension => Err(format!(
+            "Output extension {:?} does not match the {} format",
+            extension.unwrap(),
+            format.extension()
+        )),
+        (Some(format), _) => Ok(format),
+        (None, Some(from_extension)) => Ok(from_extension),
+        (None, None) => Ok(ImageFormat::Png),
+    }
+}
+
+/// Find the container to record video in, named either by its extension or
+/// its ffmpeg format.
+fn video_container(name: &str) -> Result<&'static str, String> {
+    VIDEO_CONTAINERS
+        .iter()
+        .find(|(extension, container)| {
+            extension.eq_ignore_ascii_case(name) || container.eq_ignore_ascii_case(name)
+        })
+        .map(|&(_, container)| container)
+        .ok_or_else(|| format!("{:?} is not a known video container", name))
+}
+
+/// Determine the video container from the format and the extension of the
+/// output path.
+///
+/// The extension must be for a video container when capturing video and not
+/// for one when capturing an image, and must match the format if both are
+/// given.
+fn resolve_container(
+    mode: CaptureMode,
+    format: Option<&str>,
+    output: Option<&Path>,
+) -> Result<Option<&'static str>, String> {
+    let format = format.map(video_container).transpose()?;
+    let extension = match output.and_then(Path::extension).and_then(|e| e.to_str()) {
+        Some(extension) => extension,
+        None => return Ok(format),
+    };
+    let container = VIDEO_CONTAINERS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+        .map(|&(_, container)| container);
+
+    match (mode, container) {
+        (Image, Some(_)) => Err(format!(
+            "Output extension {:?} is for video, not images",
+            extension
+        )),
+        (Video(_), None) if extension.parse::<ImageFormat>().is_ok() => Err(format!(
+            "Output extension {:?} is for images, not video",
+            extension
+        )),
+        (Video(_), None) => Err(format!(
+            "Output extension {:?} is not a known video container",
+            extension
+        )),
+        (Gif(_), _) if !extension.eq_ignore_ascii_case("gif") => {
+            Err(format!("Output extension {:?} is not for GIFs", extension))
+        }
+        (Gif(_), _) => Ok(None),
+        (Audio, Some(_)) => Err(format!(
+            "Output extension {:?} is for video, not audio",
+            extension
+        )),
+        (Audio, None) if extension.parse::<ImageFormat>().is_ok() => Err(format!(
+            "Output extension {:?} is for images, not audio",
+            extension
+        )),
+        (Audio, None) => Ok(None),
+        (_, Some(container)) if format.is_some_and(|format| format != container) => Err(format!(
+            "Output extension {:?} does not match the {} container",
+            extension,
+            format.unwrap()
+        )),
+        (_, container) => Ok(container),
+    }
+}
+
+/// An error for arguments that can't be used together.
+fn conflict(message: &str) -> clap::Error {
+    clap::Error::with_description(message, ErrorKind::ArgumentConflict)
+}
+
+/// An error for an argument with an invalid value.
+fn invalid(message: &str) -> clap::Error {
+    clap::Error::with_description(message, ErrorKind::InvalidValue)
+}
+
+/// Parse the value of an argument, if it was given.
+fn parse_arg<T, E>(
+    matches: &ArgMatches,
+    name: &str,
+    parse: impl Fn(&str) -> Result<T, E>,
+) -> Result<Option<T>, clap::Error> {
+    matches
+        .value_of(name)
+        .map(|value| {
+            parse(value).map_err(|_| invalid(&format!("{:?} is not valid for --{}", value, name)))
+        })
+        .transpose()
+}
+
+/// Parse a `COLUMNSxROWS` grid.
+fn parse_grid(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("{:?} is not a grid such as 4x4", value);
+    let (columns, rows) = value.split_once('x').ok_or_else(invalid)?;
+    let columns = columns.parse().map_err(|_| invalid())?;
+    let rows = rows.parse().map_err(|_| invalid())?;
+    if columns == 0 || rows == 0 {
+        return Err(invalid());
+    }
+    Ok((columns, rows))
+}
+
+/// Parse an `X,Y` position.
+fn parse_point(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("{:?} is not a position of the form X,Y", value);
+    let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+    let x = x.trim().parse().map_err(|_| invalid())?;
+    let y = y.trim().parse().map_err(|_| invalid())?;
+    Ok((x, y))
+}
+
+/// Check a size to scale to is `W:H`, where each side is a number of pixels
+/// or negative to keep the aspect ratio.
+fn validate_scale(value: &str) -> Result<(), String> {
+    let valid = value.split_once(':').is_some_and(|(width, height)| {
+        let sides = [width, height].map(|side| side.parse::<i32>().ok());
+        match sides {
+            [Some(width), Some(height)] => width != 0 && height != 0 && (width > 0 || height > 0),
+            _ => false,
+        }
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "{:?} is not a size such as 1280:720 or 1280:-1",
+            value
+        ))
+    }
+}
+
+/// Parse a bitrate in bits per second, either as a number or with a `k` or
+/// `M` suffix (`800k`, `4M`).
+fn parse_bitrate(value: &str) -> Result<u64, String> {
+    let invalid = || format!("{:?} is not a bitrate such as 4M or 800k", value);
+    let (number, scale) = match value.trim().char_indices().last() {
+        Some((i, 'k')) | Some((i, 'K')) => (&value.trim()[..i], 1_000),
+        Some((i, 'M')) => (&value.trim()[..i], 1_000_000),
+        _ => (value.trim(), 1),
+    };
+    // The rate buffer holds twice the bitrate, so that must fit as well.
+    match number.parse::<u64>() {
+        Ok(number) if number > 0 => number
+            .checked_mul(scale)
+            .filter(|bitrate| bitrate.checked_mul(2).is_some())
+            .ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parse a duration in seconds, either as a number of seconds (`90`), with
+/// units (`1m30s`, `2h`), or as `HH:MM:SS` or `MM:SS` (`00:01:30`).
+fn parse_duration(value: &str) -> Result<u64, String> {
+    let invalid = || {
+        format!(
+            "{:?} is not a duration such as 90, 1m30s or 00:01:30",
+            value
+        )
+    };
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse() {
+        return Ok(seconds);
+    } else if value.is_empty() {
+        return Err(invalid());
+    }
+
+    if value.contains(':') {
+        let parts = value
+            .split(':')
+            .map(|part| part.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Durations too long to count in seconds are as invalid as any other.
+        let clock = |hours: u64, minutes: u64, seconds: u64| {
+            hours
+                .checked_mul(60)?
+                .checked_add(minutes)?
+                .checked_mul(60)?
+                .checked_add(seconds)
+        };
+        return match parts[..] {
+            [minutes, seconds] if seconds < 60 => clock(0, minutes, seconds).ok_or_else(invalid),
+            [hours, minutes, seconds] if minutes < 60 && seconds < 60 => {
+                clock(hours, minutes, seconds).ok_or_else(invalid)
+            }
+            _ => Err(invalid()),
+        };
+    }
+
+    // Units must be given from largest to smallest, each at most once.
+    let mut units = [('h', 3600), ('m', 60), ('s', 1)].iter();
+    let mut seconds: u64 = 0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let number: u64 = rest[..end].parse().map_err(|_| invalid())?;
+        let unit = rest[end..].chars().next().ok_or_else(invalid)?;
+        let &(_, scale) = units.find(|(name, _)| *name == unit).ok_or_else(invalid)?;
+        seconds = number
+            .checked_mul(scale)
+            .and_then(|unit_seconds| seconds.checked_add(unit_seconds))
+            .ok_or_else(invalid)?;
+        rest = &rest[end + unit.len_utf8()..];
+    }
+    Ok(seconds)
+}
+
+/// Possible regions of the screen.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ScreenRegion {
+    #[default]
+    Screen,
+    Window,
+    Select,
+    ActiveOutput,
+    /// The whole root window, spanning every monitor
+    AllScreens,
+    /// An exact area of the screen in pixels
+    Geometry {
+        width: u32,
+        height: u32,
+        x: i32,
+        y: i32,
+    },
 }
 pub use self::ScreenRegion::*;
 
-impl Default for ScreenRegion {
-    fn default() -> Self {
-        Screen
+impl ScreenRegion {
+    /// The name the region is given with `-r`, or `geometry` for exact areas.
+    pub fn name(self) -> &'static str {
+        match self {
+            Screen => "screen",
+            Window => "window",
+            Select => "select",
+            ActiveOutput => "active-output",
+            AllScreens => "all-screens",
+            Geometry { .. } => "geometry",
+        }
     }
 }
 
@@ -101,23 +2849,844 @@ impl FromStr for ScreenRegion {
             "screen" => Ok(Screen),
             "window" => Ok(Window),
             "select" => Ok(Select),
-            _ => Err(()),
+            "active-output" => Ok(ActiveOutput),
+            "all-screens" => Ok(AllScreens),
+            _ => parse_region_geometry(s).map_err(|_| ()),
+        }
+    }
+}
+
+/// Parse an exact region of the screen given as `WxH+X+Y`.
+fn parse_region_geometry(value: &str) -> Result<ScreenRegion, String> {
+    let invalid = || format!("{:?} is not a geometry of the form WxH+X+Y", value);
+    let (width, rest) = value.split_once('x').ok_or_else(invalid)?;
+    let mut parts = rest.split('+');
+    let height = parts.next().ok_or_else(invalid)?;
+    let (x, y) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(x), Some(y), None) => (x, y),
+        _ => return Err(invalid()),
+    };
+
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+    Ok(Geometry {
+        width,
+        height,
+        x: x.parse().map_err(|_| invalid())?,
+        y: y.parse().map_err(|_| invalid())?,
+    })
+}
+
+/// Parse an `+X+Y` offset, where either may be negative such as `-8+0`.
+fn parse_offset(value: &str) -> Result<(i32, i32), String> {
+    let invalid = || format!("{:?} is not an offset of the form +X+Y", value);
+    let split = value
+        .char_indices()
+        .skip(1)
+        .find(|&(_, c)| c == '+' || c == '-')
+        .map(|(i, _)| i)
+        .ok_or_else(invalid)?;
+    let (x, y) = value.split_at(split);
+    if !x.starts_with(['+', '-']) {
+        return Err(invalid());
+    }
+    let x = x.parse().map_err(|_| invalid())?;
+    let y = y.parse().map_err(|_| invalid())?;
+    Ok((x, y))
+}
+
+/// A monitor given by its index or output name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorId {
+    Index(usize),
+    Name(String),
+}
+
+impl FromStr for MonitorId {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(index) => Ok(MonitorId::Index(index)),
+            Err(_) if !s.is_empty() => Ok(MonitorId::Name(s.to_owned())),
+            Err(_) => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for MonitorId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MonitorId::Index(index) => write!(f, "#{}", index),
+            MonitorId::Name(name) => f.write_str(name),
         }
     }
 }
 
 /// Possible capture modes.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum CaptureMode {
     /// Capture an image
+    #[default]
     Image,
     /// Capture a video at a given framerate
     Video(u64),
+    /// Capture an animated GIF at a given framerate
+    Gif(u64),
+    /// Record audio without video
+    Audio,
 }
 pub use self::CaptureMode::*;
 
-impl Default for CaptureMode {
-    fn default() -> Self {
-        Image
+/// Formats images can be saved in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpg,
+    Webp,
+    Ppm,
+}
+
+impl ImageFormat {
+    /// The file extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpg => "jpg",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+
+    /// The MIME type of the format.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Ppm => "image/x-portable-pixmap",
+        }
+    }
+
+    /// The ffmpeg encoder for the format.
+    pub fn encoder(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpg => "mjpeg",
+            ImageFormat::Webp => "libwebp",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "jpg" | "jpeg" => Ok(ImageFormat::Jpg),
+            "webp" => Ok(ImageFormat::Webp),
+            "ppm" => Ok(ImageFormat::Ppm),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Programs that can take screenshots on X11.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotTool {
+    GnomeScreenshot,
+    Scrot,
+    Maim,
+    Import,
+}
+
+impl ScreenshotTool {
+    /// Tools in the order they are preferred when none is chosen.
+    pub const ALL: &'static [ScreenshotTool] = &[
+        ScreenshotTool::GnomeScreenshot,
+        ScreenshotTool::Scrot,
+        ScreenshotTool::Maim,
+        ScreenshotTool::Import,
+    ];
+
+    /// The program that runs the tool.
+    pub fn program(self) -> &'static str {
+        match self {
+            ScreenshotTool::GnomeScreenshot => "gnome-screenshot",
+            ScreenshotTool::Scrot => "scrot",
+            ScreenshotTool::Maim => "maim",
+            ScreenshotTool::Import => "import",
+        }
+    }
+
+    /// The arguments to capture a region into a file.
+    ///
+    /// maim and import need the ID of the window to capture, which is only
+    /// used for window capture. Regions other than the focused window or a
+    /// selection capture the whole screen.
+    pub fn region_args(
+        self,
+        region: ScreenRegion,
+        window: Option<&str>,
+        filename: &str,
+    ) -> Vec<String> {
+        let mut args: Vec<&str> = match (self, region) {
+            (ScreenshotTool::GnomeScreenshot, Window) => vec!["-B", "-w", "-f"],
+            (ScreenshotTool::GnomeScreenshot, Select) => vec!["-B", "-a", "-f"],
+            (ScreenshotTool::GnomeScreenshot, _) => vec!["-B", "-f"],
+            (ScreenshotTool::Scrot, Window) => vec!["-u"],
+            (ScreenshotTool::Scrot, Select) => vec!["-s"],
+            (ScreenshotTool::Scrot, _) => vec![],
+            (ScreenshotTool::Maim, Window) => vec!["-i", window.unwrap_or("root")],
+            (ScreenshotTool::Maim, Select) => vec!["-s"],
+            (ScreenshotTool::Maim, _) => vec![],
+            (ScreenshotTool::Import, Window) => vec!["-window", window.unwrap_or("root")],
+            (ScreenshotTool::Import, Select) => vec![],
+            (ScreenshotTool::Import, _) => vec!["-window", "root"],
+        };
+        args.push(filename);
+        args.into_iter().map(str::to_owned).collect()
+    }
+
+    /// Whether the tool can save images in a format.
+    ///
+    /// gnome-screenshot only saves PNG, while import saves anything
+    /// ImageMagick can write.
+    pub fn writes(self, format: ImageFormat) -> bool {
+        match self {
+            ScreenshotTool::GnomeScreenshot => format == ImageFormat::Png,
+            ScreenshotTool::Scrot | ScreenshotTool::Maim => {
+                matches!(format, ImageFormat::Png | ImageFormat::Jpg)
+            }
+            ScreenshotTool::Import => true,
+        }
+    }
+
+    /// The arguments to save an image in a format, which most tools take
+    /// from the extension instead.
+    pub fn format_args(self, format: ImageFormat) -> Vec<&'static str> {
+        match self {
+            ScreenshotTool::Maim => vec!["-f", format.extension()],
+            _ => vec![],
+        }
+    }
+
+    /// The arguments to include or leave out the pointer.
+    pub fn cursor_args(self, cursor: bool) -> &'static [&'static str] {
+        match (self, cursor) {
+            (ScreenshotTool::GnomeScreenshot | ScreenshotTool::Scrot, true) => &["-p"],
+            (ScreenshotTool::Maim, false) => &["-u"],
+            _ => &[],
+        }
+    }
+}
+
+impl FromStr for ScreenshotTool {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ScreenshotTool::ALL
+            .iter()
+            .copied()
+            .find(|tool| tool.program() == s)
+            .ok_or(())
+    }
+}
+
+/// Corrections to the position of the capture region.
+#[derive(Debug, Clone, Copy)]
+pub enum OffsetCorrection {
+    /// Include the frame drawn around windows by the window manager
+    FrameExtents,
+    /// Move the region by a number of pixels
+    Offset(i32, i32),
+}
+
+impl FromStr for OffsetCorrection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "auto" {
+            return Ok(OffsetCorrection::FrameExtents);
+        }
+
+        let invalid = || format!("{:?} is not 'auto' or an offset of the form DX,DY", s);
+        let (dx, dy) = s.split_once(',').ok_or_else(invalid)?;
+        let dx = dx.trim().parse().map_err(|_| invalid())?;
+        let dy = dy.trim().parse().map_err(|_| invalid())?;
+        Ok(OffsetCorrection::Offset(dx, dy))
+    }
+}
+
+/// Text to mark captures with.
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    /// Template for the text
+    pub text: String,
+    pub position: WatermarkPosition,
+    /// Opacity from 0 to 1
+    pub opacity: f64,
+    /// Font size in pixels
+    pub size: u32,
+    /// Font file, where none uses fontconfig's default font
+    pub font: Option<PathBuf>,
+}
+
+/// A webcam to overlay on recordings.
+#[derive(Debug, Clone)]
+pub struct Webcam {
+    /// The video4linux device
+    pub device: PathBuf,
+    pub position: WebcamPosition,
+}
+
+/// The layout of a contact sheet.
+#[derive(Debug, Clone)]
+pub struct ContactSheet {
+    pub columns: u32,
+    pub rows: u32,
+    /// Width of each frame in pixels
+    pub width: u32,
+}
+
+/// Where to place a watermark.
+#[derive(Debug, Clone, Copy)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl FromStr for WatermarkPosition {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top-left" => Ok(WatermarkPosition::TopLeft),
+            "top-right" => Ok(WatermarkPosition::TopRight),
+            "bottom-left" => Ok(WatermarkPosition::BottomLeft),
+            "bottom-right" => Ok(WatermarkPosition::BottomRight),
+            "center" => Ok(WatermarkPosition::Center),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Corners to overlay a webcam in.
+#[derive(Debug, Clone, Copy)]
+pub enum WebcamPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl WebcamPosition {
+    /// The position of the webcam for ffmpeg's overlay filter.
+    pub fn overlay(self) -> &'static str {
+        match self {
+            WebcamPosition::TopLeft => "x=10:y=10",
+            WebcamPosition::TopRight => "x=W-w-10:y=10",
+            WebcamPosition::BottomLeft => "x=10:y=H-h-10",
+            WebcamPosition::BottomRight => "x=W-w-10:y=H-h-10",
+        }
+    }
+}
+
+impl FromStr for WebcamPosition {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top-left" => Ok(WebcamPosition::TopLeft),
+            "top-right" => Ok(WebcamPosition::TopRight),
+            "bottom-left" => Ok(WebcamPosition::BottomLeft),
+            "bottom-right" => Ok(WebcamPosition::BottomRight),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Timing sources for captured video frames.
+#[derive(Debug, Clone, Copy)]
+pub enum InputSync {
+    /// Timestamp frames with the wall clock time they were read
+    Wallclock,
+    /// Keep the evenly paced timestamps of the capture device
+    Monotonic,
+}
+
+impl InputSync {
+    /// Whether input timestamps should come from the wall clock.
+    pub fn wallclock(self) -> bool {
+        match self {
+            InputSync::Wallclock => true,
+            InputSync::Monotonic => false,
+        }
+    }
+}
+
+impl FromStr for InputSync {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wallclock" => Ok(InputSync::Wallclock),
+            "monotonic" => Ok(InputSync::Monotonic),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How the size of encoded video is controlled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateControl {
+    /// Vary the bitrate to keep a constant quality
+    Crf(u32),
+    /// Keep to a bitrate in bits per second
+    Bitrate(u64),
+}
+
+impl RateControl {
+    /// The ffmpeg arguments for encoding video this way.
+    pub fn args(self) -> Vec<String> {
+        match self {
+            RateControl::Crf(crf) => vec!["-crf".to_owned(), crf.to_string()],
+            RateControl::Bitrate(bitrate) => vec![
+                "-b:v".to_owned(),
+                bitrate.to_string(),
+                "-maxrate".to_owned(),
+                bitrate.to_string(),
+                "-bufsize".to_owned(),
+                (bitrate * 2).to_string(),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for RateControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RateControl::Crf(crf) => write!(f, "CRF {}", crf),
+            RateControl::Bitrate(bitrate) => write!(f, "{} bit/s", bitrate),
+        }
+    }
+}
+
+/// Formats for reporting recording progress.
+#[derive(Debug, Clone, Copy)]
+pub enum ProgressFormat {
+    /// A JSON object per line
+    Json,
+}
+
+impl FromStr for ProgressFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_format_defaults_to_png() {
+        assert_eq!(resolve_image_format(None, None), Ok(ImageFormat::Png));
+    }
+
+    #[test]
+    fn image_format_from_format() {
+        assert_eq!(
+            resolve_image_format(Some("webp"), None),
+            Ok(ImageFormat::Webp)
+        );
+    }
+
+    #[test]
+    fn image_format_from_output_extension() {
+        let output = Path::new("/tmp/capture.jpeg");
+        assert_eq!(
+            resolve_image_format(None, Some(output)),
+            Ok(ImageFormat::Jpg)
+        );
+    }
+
+    #[test]
+    fn image_format_agrees_with_output_extension() {
+        let output = Path::new("/tmp/capture.jpg");
+        assert_eq!(
+            resolve_image_format(Some("jpg"), Some(output)),
+            Ok(ImageFormat::Jpg)
+        );
+    }
+
+    #[test]
+    fn image_format_conflicts_with_output_extension() {
+        let output = Path::new("/tmp/capture.png");
+        assert!(resolve_image_format(Some("ppm"), Some(output)).is_err());
+    }
+
+    #[test]
+    fn image_format_ignores_unknown_output_extension() {
+        let output = Path::new("/tmp/capture.img");
+        assert_eq!(
+            resolve_image_format(Some("ppm"), Some(output)),
+            Ok(ImageFormat::Ppm)
+        );
+        assert_eq!(
+            resolve_image_format(None, Some(output)),
+            Ok(ImageFormat::Png)
+        );
+    }
+
+    #[test]
+    fn duration_in_seconds() {
+        assert_eq!(parse_duration("90"), Ok(90));
+    }
+
+    #[test]
+    fn duration_with_units() {
+        assert_eq!(parse_duration("1m30s"), Ok(90));
+        assert_eq!(parse_duration("2h"), Ok(7200));
+    }
+
+    #[test]
+    fn duration_as_clock_time() {
+        assert_eq!(parse_duration("00:01:30"), Ok(90));
+        assert_eq!(parse_duration("1:30"), Ok(90));
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for duration in &["", "1x", "30s1m", "1m1m", "00:90", "m", "1:2:3:4"] {
+            assert!(parse_duration(duration).is_err(), "{:?}", duration);
+        }
+    }
+
+    #[test]
+    fn overlong_durations() {
+        for duration in &[
+            "99999999999999999h",
+            "5124095576030431h16s",
+            "99999999999999999:00:00",
+            "999999999999999999:00",
+        ] {
+            assert!(parse_duration(duration).is_err(), "{:?}", duration);
+        }
+        assert_eq!(
+            parse_duration("5124095576030431h"),
+            Ok(5124095576030431 * 3600)
+        );
+    }
+
+    #[test]
+    fn scales() {
+        for scale in &["1280:720", "1280:-1", "-2:720"] {
+            assert!(validate_scale(scale).is_ok(), "{:?}", scale);
+        }
+        for scale in &["", "1280", "1280x720", "0:720", "-1:-1", "w:h", "1:2:3"] {
+            assert!(validate_scale(scale).is_err(), "{:?}", scale);
+        }
+    }
+
+    #[test]
+    fn bitrates() {
+        assert_eq!(parse_bitrate("4M"), Ok(4_000_000));
+        assert_eq!(parse_bitrate("800k"), Ok(800_000));
+        assert_eq!(parse_bitrate("128000"), Ok(128_000));
+        for bitrate in &["", "M", "0k", "4G", "1.5M", "-4M"] {
+            assert!(parse_bitrate(bitrate).is_err(), "{:?}", bitrate);
+        }
+        let largest = (u64::MAX / 2).to_string();
+        assert_eq!(parse_bitrate(&largest), Ok(u64::MAX / 2));
+        for bitrate in &[(u64::MAX / 2 + 1).to_string(), u64::MAX.to_string()] {
+            assert!(parse_bitrate(bitrate).is_err(), "{:?}", bitrate);
+        }
+        assert!(parse_bitrate(&format!("{}M", u64::MAX / 2_000_000 + 1)).is_err());
+        assert_eq!(
+            RateControl::Bitrate(u64::MAX / 2).args()[5],
+            (u64::MAX - 1).to_string()
+        );
+        assert_eq!(
+            RateControl::Bitrate(4_000_000).args(),
+            ["-b:v", "4000000", "-maxrate", "4000000", "-bufsize", "8000000"]
+        );
+        assert_eq!(RateControl::Crf(16).args(), ["-crf", "16"]);
+    }
+
+    #[test]
+    fn container_from_output_extension() {
+        let output = Path::new("/tmp/demo.mp4");
+        assert_eq!(
+            resolve_container(Video(30), None, Some(output)),
+            Ok(Some("mp4"))
+        );
+        assert_eq!(resolve_container(Video(30), None, None), Ok(None));
+    }
+
+    #[test]
+    fn container_from_format() {
+        let output = Path::new("/tmp/demo.mp4");
+        assert_eq!(
+            resolve_container(Video(30), Some("mp4"), None),
+            Ok(Some("mp4"))
+        );
+        assert_eq!(
+            resolve_container(Video(30), Some("MP4"), Some(output)),
+            Ok(Some("mp4"))
+        );
+        assert!(resolve_container(Video(30), Some("webm"), Some(output)).is_err());
+        assert!(resolve_container(Video(30), Some("avi"), None).is_err());
+    }
+
+    #[test]
+    fn container_conflicts_with_mode() {
+        let image = Path::new("/tmp/demo.png");
+        let video = Path::new("/tmp/demo.mkv");
+        assert!(resolve_container(Video(30), None, Some(image)).is_err());
+        assert!(resolve_container(Image, None, Some(video)).is_err());
+    }
+
+    #[test]
+    fn toml_values() {
+        assert_eq!(toml_value(r#""window""#), Ok("window".to_owned()));
+        assert_eq!(toml_value(r"'a\b' # path"), Ok(r"a\b".to_owned()));
+        assert_eq!(toml_value(r#""say \"hi\"""#), Ok(r#"say "hi""#.to_owned()));
+        assert_eq!(toml_value("1_000 # bitrate"), Ok("1000".to_owned()));
+        assert_eq!(toml_value("-5"), Ok("-5".to_owned()));
+        assert_eq!(toml_value("true"), Ok("true".to_owned()));
+        assert!(toml_value("window").is_err());
+        assert!(toml_value(r#""open"#).is_err());
+        assert!(toml_value("[1, 2]").is_err());
+        assert!(toml_value(r#""a" "b""#).is_err());
+    }
+
+    #[test]
+    fn settings_follow_the_arguments() {
+        assert_eq!(setting("region"), Some(("-r".to_owned(), true)));
+        assert_eq!(setting("monitor"), Some(("--monitor".to_owned(), true)));
+        assert_eq!(
+            setting("no-shadow"),
+            Some(("--no-shadow".to_owned(), false))
+        );
+        assert_eq!(setting("env-file"), None);
+        assert_eq!(setting("probe-only"), None);
+        assert_eq!(setting("no-such-setting"), None);
+    }
+
+    #[test]
+    fn default_config_keys_are_settings() {
+        for line in DEFAULT_CONFIG.lines() {
+            if let Some((key, _)) = line.trim_start_matches("# ").split_once(" = ") {
+                assert!(setting(key).is_some(), "{} is not a setting", key);
+            }
+        }
+    }
+
+    #[test]
+    fn settings_are_arguments() {
+        let unknown = |args: &[&str]| {
+            let matches = Config::args().get_matches_from_safe(args);
+            matches.is_err_and(|error| error.kind == ErrorKind::UnknownArgument)
+        };
+        for &(name, takes_value) in SETTINGS {
+            let (flag, _) = setting(name).unwrap();
+            assert!(
+                !unknown(&["screencap", &flag]),
+                "{} is not an argument",
+                name
+            );
+            // A value after a flag that takes none is an unexpected argument.
+            assert_eq!(
+                unknown(&["screencap", &flag, "1"]),
+                !takes_value,
+                "{} takes a value",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn parse_region_geometries() {
+        let region = parse_region_geometry("1280x720+100+50").unwrap();
+        assert!(matches!(
+            region,
+            Geometry {
+                width: 1280,
+                height: 720,
+                x: 100,
+                y: 50
+            }
+        ));
+        assert!(matches!("screen".parse(), Ok(Screen)));
+        assert!(parse_region_geometry("1280x720").is_err());
+        assert!(parse_region_geometry("0x720+0+0").is_err());
+        assert!(parse_region_geometry("1280x720+1+2+3").is_err());
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(parse_offset("+10+20"), Ok((10, 20)));
+        assert_eq!(parse_offset("-8+0"), Ok((-8, 0)));
+        assert_eq!(parse_offset("+0-16"), Ok((0, -16)));
+        assert!(parse_offset("10+20").is_err());
+        assert!(parse_offset("+10").is_err());
+        assert!(parse_offset("+10+20+30").is_err());
+        assert!(parse_offset("+x+1").is_err());
+    }
+
+    #[test]
+    fn video_container_by_extension_or_format() {
+        assert_eq!(video_container("mkv"), Ok("matroska"));
+        assert_eq!(video_container("Matroska"), Ok("matroska"));
+        assert!(video_container("avi").is_err());
+    }
+
+    #[test]
+    fn gif_output_is_not_a_container() {
+        let gif = Path::new("/tmp/demo.GIF");
+        let video = Path::new("/tmp/demo.mkv");
+        assert_eq!(resolve_container(Gif(15), None, Some(gif)), Ok(None));
+        assert!(resolve_container(Gif(15), None, Some(video)).is_err());
+        let audio = Path::new("clip.opus");
+        assert_eq!(resolve_container(Audio, None, Some(audio)), Ok(None));
+        assert!(resolve_container(Audio, None, Some(video)).is_err());
+        assert!(resolve_container(Audio, None, Some(Path::new("clip.png"))).is_err());
+    }
+
+    #[test]
+    fn screenshot_tool_region_args() {
+        assert_eq!(
+            ScreenshotTool::GnomeScreenshot.region_args(Select, None, "a.png"),
+            ["-B", "-a", "-f", "a.png"]
+        );
+        assert_eq!(
+            ScreenshotTool::Maim.region_args(Window, Some("0x42"), "a.png"),
+            ["-i", "0x42", "a.png"]
+        );
+        assert_eq!(
+            ScreenshotTool::Import.region_args(Screen, None, "a.png"),
+            ["-window", "root", "a.png"]
+        );
+        assert_eq!("scrot".parse(), Ok(ScreenshotTool::Scrot));
+        assert_eq!(ScreenshotTool::Scrot.cursor_args(true), ["-p"]);
+        assert_eq!(ScreenshotTool::Maim.cursor_args(false), ["-u"]);
+        assert!(!ScreenshotTool::GnomeScreenshot.writes(ImageFormat::Jpg));
+        assert!(ScreenshotTool::Import.writes(ImageFormat::Webp));
+    }
+
+    #[test]
+    fn library_captures_use_the_defaults() {
+        let config = Config::for_image(Path::new("shot.jpg"), Screen).unwrap();
+        assert!(matches!(config.mode(), Image));
+        assert_eq!(config.image_format(), ImageFormat::Jpg);
+        assert_eq!(config.name_template(), DEFAULT_TEMPLATE);
+        assert_eq!(config.repeat(), 1);
+        assert!(config.invocation().is_empty());
+
+        let options = Options {
+            audio: false,
+            ..Options::default()
+        };
+        let config = Config::for_video(Path::new("clip.webm"), Window, &options).unwrap();
+        assert!(matches!(config.mode(), Video(30)));
+        assert_eq!(config.container(), Some("webm"));
+        assert!(config.no_audio());
+    }
+
+    #[test]
+    fn invalid_library_captures() {
+        assert!(Config::for_image(Path::new("-"), Screen).is_err());
+        assert!(Config::for_image(Path::new("shot.mkv"), Screen).is_err());
+        assert!(Config::for_image(Path::new("/"), Screen).is_err());
+        let options = Options {
+            framerate: 0,
+            ..Options::default()
+        };
+        assert!(Config::for_video(Path::new("clip.mkv"), Screen, &options).is_err());
+    }
+
+    /// Parse the arguments with settings from a file in front of them.
+    fn with_file(settings: &[&[&str]], args: &[&str]) -> Result<Config, clap::Error> {
+        let owned = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        let settings = settings.iter().map(|setting| owned(setting)).collect();
+        let args = with_settings(settings, owned(args));
+        let matches = Config::args().get_matches_from_safe(&args)?;
+        Config::from_settled(matches, args, None)
+    }
+
+    #[test]
+    fn settings_give_way_to_conflicting_arguments() {
+        let config = with_file(
+            &[&["--crf", "20"]],
+            &["screencap", "-m", "video", "--bitrate", "4M"],
+        )
+        .unwrap();
+        assert_eq!((config.crf(), config.bitrate()), (None, Some(4_000_000)));
+
+        let config = with_file(
+            &[&["--audio-source", "mic"]],
+            &["screencap", "-m", "video", "--desktop-audio"],
+        )
+        .unwrap();
+        assert_eq!(config.audio_source(), None);
+        assert!(config.desktop_audio());
+
+        let config = with_file(
+            &[&["-r", "window"]],
+            &["screencap", "--region-geometry", "10x20+1+2"],
+        )
+        .unwrap();
+        assert!(matches!(config.region(), Geometry { width: 10, .. }));
+    }
+
+    #[test]
+    fn outputs_need_a_file_name() {
+        for output in &["/", "shots/.."] {
+            let error = with_file(&[], &["screencap", "-o", output]).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::InvalidValue, "{:?}", output);
+        }
+        let config = with_file(&[], &["screencap", "-o", "shots/shot.png"]).unwrap();
+        assert_eq!(config.output(), Some(Path::new("shots/shot.png")));
+    }
+
+    #[test]
+    fn settings_apply_without_conflicts() {
+        let config = with_file(&[&["--crf", "20"]], &["screencap", "-m", "video"]).unwrap();
+        assert_eq!(config.crf(), Some(20));
+        assert!(with_file(&[&["--crf", "x"]], &["screencap", "-m", "video"]).is_err());
+    }
+
+    #[test]
+    fn env_file_settings_give_way_to_conflicting_arguments() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("screencap-test.{}.env", std::process::id()));
+        fs::write(&path, "CRF=20\nPRESET=slow\n").unwrap();
+        let args: Vec<OsString> = ["screencap", "-m", "video", "--bitrate", "4M"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let matches = Config::args().get_matches_from_safe(&args).unwrap();
+        let settings = env_file_args(path.to_str().unwrap(), &matches);
+        let _ = fs::remove_file(&path);
+
+        let args = with_settings(settings.unwrap(), args);
+        let matches = Config::args().get_matches_from_safe(&args).unwrap();
+        let config = Config::from_settled(matches, args, None).unwrap();
+        assert_eq!((config.crf(), config.preset()), (None, Some("slow")));
     }
 }
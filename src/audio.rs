@@ -0,0 +1,61 @@
+//! Find PulseAudio sources to record from.
+
+use std::io;
+
+use crate::exec;
+use crate::util::*;
+
+/// The source recorded from when none is chosen.
+pub const DEFAULT_AUDIO_SOURCE: &str = "default";
+
+/// The source for what is playing on the default output.
+pub const OUTPUT_MONITOR_SOURCE: &str = "@DEFAULT_MONITOR@";
+
+/// A PulseAudio source that audio can be recorded from.
+#[derive(Debug, Clone)]
+pub struct AudioSource {
+    pub name: String,
+    pub state: String,
+}
+
+/// Get the sources PulseAudio can record from.
+///
+/// `pactl list sources short` gives the index, name, driver, sample format
+/// and state of each source separated by tabs.
+pub fn audio_sources() -> io::Result<Vec<AudioSource>> {
+    Ok(command_output(exec!(pactl list sources short))?
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            Some(AudioSource {
+                name: fields.get(1)?.to_string(),
+                state: fields.get(4).unwrap_or(&"").to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Get the monitor source of the default sink, which records what is playing
+/// on the speakers.
+pub fn default_monitor_source() -> io::Result<String> {
+    command_output(exec!(pactl("get-default-sink")))?
+        .next()
+        .map(|sink| format!("{}.monitor", sink.trim()))
+        .filter(|source| source != ".monitor")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "pactl found no default sink"))
+}
+
+/// Print the sources PulseAudio can record from.
+pub fn list_audio_sources() -> io::Result<()> {
+    for source in audio_sources()? {
+        println!("{:<60} {}", source.name, source.state);
+    }
+    Ok(())
+}
+
+/// Build a filter mixing consecutive audio inputs into the `[a]` output.
+pub fn audio_mix_filter(first: usize, inputs: usize) -> String {
+    let labels: String = (first..first + inputs)
+        .map(|input| format!("[{}:a]", input))
+        .collect();
+    format!("{}amix=inputs={}[a]", labels, inputs)
+}
@@ -0,0 +1,177 @@
+//! Compare how well the available video encoders perform.
+
+use std::env::temp_dir;
+use std::fs;
+use std::io;
+use std::process::{self, Stdio};
+use std::time::Instant;
+
+use crate::exec;
+use crate::find_codecs;
+use crate::util::*;
+use crate::{eemit, emit};
+
+/// H.264 encoders to compare, where available.
+const ENCODERS: &[&str] = &["libx264", "h264_nvenc", "h264_qsv", "h264_vaapi", "h264"];
+
+/// Length of the test clip in seconds.
+const CLIP_SECONDS: u64 = 5;
+
+/// Portion of the requested framerate below which frames are being dropped.
+const SUSTAINED_PORTION: f64 = 0.95;
+
+/// The result of encoding the test clip with an encoder.
+struct Benchmark {
+    encoder: String,
+    /// How many times faster than real time the clip was encoded
+    speed: f64,
+    /// Size of the encoded clip in bytes
+    size: u64,
+}
+
+/// Encode the same test clip with each available encoder and print how fast
+/// each was and how large a file each produced.
+pub fn benchmark_encoders(resolution: &str, framerate: u64) -> io::Result<()> {
+    let encoders = find_codecs(
+        FFMPEGSupport::video_encoders()?,
+        ENCODERS,
+        FFMPEGSupport::encode,
+    );
+    emit!(
+        "Encoding a {} second {} clip at {} fps with {} encoders",
+        CLIP_SECONDS,
+        resolution,
+        framerate,
+        encoders.len()
+    );
+
+    let mut results = Vec::new();
+    let mut failed = Vec::new();
+    for encoder in encoders {
+        match encode_clip(&encoder, resolution, framerate) {
+            Some((speed, size)) => results.push(Benchmark {
+                encoder,
+                speed,
+                size,
+            }),
+            None => failed.push(encoder),
+        }
+    }
+
+    results.sort_by(|a, b| b.speed.total_cmp(&a.speed));
+    println!("{:<16}{:>10}{:>12}", "Encoder", "Speed", "Size");
+    for result in &results {
+        println!(
+            "{:<16}{:>9.2}x{:>8.2} MiB",
+            result.encoder,
+            result.speed,
+            result.size as f64 / (1024.0 * 1024.0)
+        );
+    }
+    for encoder in &failed {
+        println!("{:<16}{:>10}", encoder, "failed");
+    }
+    Ok(())
+}
+
+/// Encode the test clip, getting the speed relative to real time and the
+/// size of the encoded clip.
+fn encode_clip(encoder: &str, resolution: &str, framerate: u64) -> Option<(f64, u64)> {
+    let mut output = temp_dir();
+    output.push(format!(
+        "screencap-benchmark.{}.{}.mkv",
+        process::id(),
+        encoder
+    ));
+
+    let source = format!(
+        "testsrc2=size={}:rate={}:duration={}",
+        resolution, framerate, CLIP_SECONDS
+    );
+    let mut command = exec!(ffmpeg - hide_banner - y);
+    if encoder.ends_with("_vaapi") {
+        exec!(@(command) -vaapi_device ("/dev/dri/renderD128"));
+    }
+    exec!(@(command) -f lavfi -i (source));
+    if encoder.ends_with("_vaapi") {
+        exec!(@(command) -vf ("format=nv12,hwupload"));
+    }
+    exec!(@(command) ("-c:v") (encoder) ("-preset:v") fast -crf (16) (output.display()));
+
+    let start = Instant::now();
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()?;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let size = fs::metadata(&output).map(|metadata| metadata.len());
+    let _ = fs::remove_file(&output);
+
+    if !status.success() {
+        return None;
+    }
+    Some((CLIP_SECONDS as f64 / elapsed, size.ok()?))
+}
+
+/// Record the capture region for the length of the test clip, discarding
+/// the output, and print the average framerate achieved.
+pub fn probe_framerate(
+    resolution: &str,
+    input: &str,
+    encoder: &str,
+    framerate: u64,
+) -> io::Result<()> {
+    emit!(
+        "Recording {} at {} fps with {} for {} seconds",
+        resolution,
+        framerate,
+        encoder,
+        CLIP_SECONDS
+    );
+
+    let output = exec!(ffmpeg
+        -hide_banner
+        -f x11grab
+        -framerate (framerate)
+        -video_size (resolution)
+        -i (input)
+        -t (CLIP_SECONDS)
+        ("-c:v") (encoder) ("-preset:v") fast -crf (16)
+        -f null
+        ("-")
+    )
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .output()?;
+    if !output.status.success() {
+        let message = format!("Recording failed with {}", output.status);
+        return Err(io::Error::other(message));
+    }
+
+    // Progress lines end with carriage returns, and the last has the average.
+    let achieved = String::from_utf8_lossy(&output.stderr)
+        .rsplit(['\r', '\n'])
+        .find_map(parse_fps)
+        .ok_or_else(|| io::Error::other("ffmpeg did not report a framerate"))?;
+
+    println!("Achieved {:.1} of {} fps", achieved, framerate);
+    if achieved < framerate as f64 * SUSTAINED_PORTION {
+        eemit!(
+            "Warning: frames will be dropped at {} fps, try -R {}",
+            framerate,
+            (achieved.floor() as u64).max(1)
+        );
+    }
+    Ok(())
+}
+
+/// Get the framerate from an ffmpeg progress line.
+///
+/// Progress lines include a framerate such as `fps= 29` or `fps=59.8`.
+fn parse_fps(line: &str) -> Option<f64> {
+    let (_, fps) = line.split_once("fps=")?;
+    fps.split_whitespace().next()?.parse().ok()
+}
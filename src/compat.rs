@@ -0,0 +1,131 @@
+//! Check which codecs a container can hold.
+
+/// The codecs a container can hold, where none means any codec.
+struct Container {
+    /// ffmpeg muxer name followed by any other names it is known by
+    names: &'static [&'static str],
+    video: Option<&'static [&'static str]>,
+    audio: Option<&'static [&'static str]>,
+}
+
+impl Container {
+    fn has_name(&self, name: &str) -> bool {
+        self.names.contains(&name)
+    }
+
+    fn holds_video(&self, codec: &str) -> bool {
+        self.video.is_none_or(|codecs| codecs.contains(&codec))
+    }
+
+    fn holds_audio(&self, codec: &str) -> bool {
+        self.audio.is_none_or(|codecs| codecs.contains(&codec))
+    }
+}
+
+/// Containers with the codecs they can hold.
+const CONTAINERS: &[Container] = &[
+    Container {
+        names: &["matroska", "mkv"],
+        video: None,
+        audio: None,
+    },
+    Container {
+        names: &["mp4", "m4v"],
+        video: Some(&["h264", "hevc", "av1", "vp9", "mpeg4"]),
+        audio: Some(&["aac", "mp3", "opus", "flac", "ac3", "alac"]),
+    },
+    Container {
+        names: &["mov"],
+        video: Some(&["h264", "hevc", "prores", "mpeg4"]),
+        audio: Some(&["aac", "alac", "mp3", "pcm_s16le"]),
+    },
+    Container {
+        names: &["webm"],
+        video: Some(&["vp8", "vp9", "av1"]),
+        audio: Some(&["vorbis", "opus"]),
+    },
+    Container {
+        names: &["ogg", "ogv"],
+        video: Some(&["theora", "vp8"]),
+        audio: Some(&["vorbis", "opus", "flac"]),
+    },
+];
+
+/// Get the codec an ffmpeg encoder produces.
+///
+/// Encoders such as `libx264` and `h264_nvenc` are named after a codec, so
+/// this only needs to handle the common naming schemes.
+pub fn encoder_codec(encoder: &str) -> &str {
+    match encoder {
+        "libx264" | "libopenh264" => "h264",
+        "libx265" => "hevc",
+        "libvpx" => "vp8",
+        "libvpx-vp9" => "vp9",
+        "libaom-av1" | "libsvtav1" | "librav1e" => "av1",
+        "libtheora" => "theora",
+        "prores_ks" | "prores_aw" => "prores",
+        "libopus" => "opus",
+        "libvorbis" => "vorbis",
+        "libmp3lame" => "mp3",
+        "libvo_aac" | "libfdk_aac" => "aac",
+        encoder => encoder.split('_').next().unwrap_or(encoder),
+    }
+}
+
+/// Check that a container can hold video and audio from the given encoders,
+/// where no audio encoder is for video without audio.
+pub fn check_compat(container: &str, video: &str, audio: Option<&str>) -> Result<(), String> {
+    let known = CONTAINERS
+        .iter()
+        .find(|known| known.has_name(container))
+        .ok_or_else(|| format!("Unknown container {:?}", container))?;
+    let (video, audio) = (encoder_codec(video), audio.map(encoder_codec));
+
+    match (
+        known.holds_video(video),
+        audio.is_none_or(|audio| known.holds_audio(audio)),
+    ) {
+        (true, true) => Ok(()),
+        (false, _) => Err(format!("{} cannot hold {} video", container, video)),
+        (_, false) => Err(format!(
+            "{} cannot hold {} audio",
+            container,
+            audio.unwrap()
+        )),
+    }
+}
+
+/// Print whether a container can hold video and audio from the given encoders
+/// and suggest alternatives if it can't.
+pub fn report_compat(container: &str, video: &str, audio: &str) {
+    let message = match check_compat(container, video, Some(audio)) {
+        Ok(()) => {
+            println!("{} can hold {} video and {} audio", container, video, audio);
+            return;
+        }
+        Err(message) => message,
+    };
+    println!("{}", message);
+
+    if let Some(known) = CONTAINERS.iter().find(|known| known.has_name(container)) {
+        if let Some(codecs) = known.video {
+            println!("Video codecs for {}: {}", container, codecs.join(", "));
+        }
+        if let Some(codecs) = known.audio {
+            println!("Audio codecs for {}: {}", container, codecs.join(", "));
+        }
+    }
+
+    let (video, audio) = (encoder_codec(video), encoder_codec(audio));
+    let alternatives: Vec<&str> = CONTAINERS
+        .iter()
+        .filter(|known| known.holds_video(video) && known.holds_audio(audio))
+        .map(|known| known.names[0])
+        .collect();
+    println!(
+        "Containers for {} video and {} audio: {}",
+        video,
+        audio,
+        alternatives.join(", ")
+    );
+}
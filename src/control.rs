@@ -0,0 +1,391 @@
+//! Control a recording with commands read from standard input.
+//!
+//! Each command is a line and each is answered with a line starting with
+//! `ok` or `error`:
+//!
+//! - `start` starts recording
+//! - `pause` or `p` pauses recording
+//! - `resume` or `r` resumes a paused recording
+//! - `marker [NAME]` marks the current time in the recording as a chapter
+//! - `snapshot` saves the screen as a PNG image next to the recording
+//! - `stop` stops recording and saves the recording
+//!
+//! The end of input is treated as `stop`.
+//!
+//! Other recordings from the terminal can be paused by typing `p` and resumed
+//! with `r`, each followed by Enter.
+
+use std::env::temp_dir;
+use std::fs;
+use std::io::{self, stdin, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::stop::Stopper;
+use crate::util::*;
+use crate::{emit, exec};
+
+/// How often a paused recording checks whether it was stopped.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A recording made of segments, one for each time recording is resumed.
+pub struct Segments {
+    directory: PathBuf,
+    extension: String,
+    segments: Vec<PathBuf>,
+    /// Length of every finished segment
+    recorded: Duration,
+    /// The segment being recorded
+    current: Option<(Child, Stopper, Instant)>,
+    /// Whether to keep the segments, as when they couldn't be joined
+    keep: bool,
+}
+
+impl Segments {
+    /// Create an empty recording whose segments have the given extension.
+    pub fn new(extension: &str) -> io::Result<Self> {
+        let mut directory = temp_dir();
+        directory.push(format!("screencap-segments.{}", process::id()));
+        fs::create_dir_all(&directory)?;
+
+        Ok(Segments {
+            directory,
+            extension: extension.to_owned(),
+            segments: Vec::new(),
+            recorded: Duration::default(),
+            current: None,
+            keep: false,
+        })
+    }
+
+    /// Whether a segment is being recorded.
+    pub fn recording(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Time recorded so far.
+    pub fn elapsed(&self) -> Duration {
+        let current = self.current.as_ref();
+        self.recorded + current.map_or(Duration::default(), |(_, _, start)| start.elapsed())
+    }
+
+    /// Start recording a new segment with the command for recording to a path,
+    /// stopped through the stopper.
+    pub fn start(
+        &mut self,
+        record: impl Fn(&Path) -> Command,
+        stopper: &Stopper,
+    ) -> io::Result<()> {
+        let mut segment = self.directory.clone();
+        segment.push(format!("{}.{}", self.segments.len(), self.extension));
+
+        let mut child = record(&segment)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(running("ffmpeg"))?;
+        stopper.attach(child.stdin.take().expect("ffmpeg standard input"));
+
+        self.segments.push(segment);
+        self.current = Some((child, stopper.clone(), Instant::now()));
+        Ok(())
+    }
+
+    /// Stop recording the current segment.
+    pub fn stop(&mut self) {
+        if let Some((mut child, stopper, start)) = self.current.take() {
+            stopper.pause();
+            let _ = child.wait();
+            self.recorded += start.elapsed();
+        }
+    }
+
+    /// Move a finished recording in as the first segment.
+    pub fn prepend(&mut self, recording: &Path) -> io::Result<()> {
+        let mut segment = self.directory.clone();
+        segment.push(format!("first.{}", self.extension));
+        fs::rename(recording, &segment)?;
+        self.segments.insert(0, segment);
+        Ok(())
+    }
+
+    /// Join the segments into a single recording, with chapters starting at
+    /// each marker.
+    ///
+    /// Returns whether anything was recorded. If the segments can't be
+    /// joined they are kept so the recording isn't lost.
+    pub fn finish(
+        mut self,
+        output: &Path,
+        format: &str,
+        markers: &[(Duration, String)],
+    ) -> io::Result<bool> {
+        self.stop();
+        let segments: Vec<&PathBuf> = self.segments.iter().filter(|s| s.exists()).collect();
+        if segments.is_empty() {
+            return Ok(false);
+        }
+
+        let mut list = self.directory.clone();
+        list.push("segments.txt");
+        let entries: String = segments
+            .iter()
+            .map(|segment| format!("file '{}'\n", segment.display()))
+            .collect();
+        fs::write(&list, entries)?;
+
+        let mut command = exec!(ffmpeg - hide_banner - y - f concat - safe(0) - i(list.display()));
+        if !markers.is_empty() {
+            let mut metadata = self.directory.clone();
+            metadata.push("chapters.txt");
+            fs::write(&metadata, chapters(markers, self.recorded))?;
+            exec!(@(command) -i (metadata.display()) -map_metadata (1) -map (0));
+        }
+        exec!(@(command) -c copy -f (format));
+
+        let status = command
+            .args(faststart_args(format))
+            .arg(output)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() {
+            self.keep = true;
+            let message = format!(
+                "Could not join the recorded segments, they are kept in {:?}",
+                self.directory
+            );
+            return Err(io::Error::other(message));
+        }
+        Ok(true)
+    }
+}
+
+impl Drop for Segments {
+    fn drop(&mut self) {
+        self.stop();
+        if !self.keep {
+            let _ = fs::remove_dir_all(&self.directory);
+        }
+    }
+}
+
+/// Escape a value for ffmpeg's metadata file format, where `=`, `;`, `#`,
+/// `\` and newlines are special.
+fn metadata_escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Write markers as ffmpeg chapter metadata.
+fn chapters(markers: &[(Duration, String)], length: Duration) -> String {
+    let mut metadata = ";FFMETADATA1\n".to_owned();
+    for (index, (start, name)) in markers.iter().enumerate() {
+        let end = markers.get(index + 1).map_or(length, |(end, _)| *end);
+        metadata.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            start.as_millis(),
+            end.as_millis(),
+            metadata_escape(name)
+        ));
+    }
+    metadata
+}
+
+/// Record as directed by commands read from standard input.
+pub fn record_with_commands(
+    output: &Path,
+    format: &str,
+    record: impl Fn(&Path) -> Command,
+    snapshot: impl Fn(&Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut segments = Segments::new(&extension(output))?;
+    let mut markers: Vec<(Duration, String)> = Vec::new();
+    let mut snapshots = 0;
+    let mut started = false;
+
+    println!("ok ready");
+    for line in stdin().lock().lines().map_while(Result::ok) {
+        let (command, argument) = match line.trim().split_once(' ') {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (line.trim(), ""),
+        };
+        // Pausing and resuming can be typed by hand between steps of a demo.
+        let command = match command {
+            "p" => "pause",
+            "r" => "resume",
+            command => command,
+        };
+
+        match command {
+            "start" if started => println!("error already started"),
+            "start" => match segments.start(&record, &Stopper::default()) {
+                Ok(()) => {
+                    started = true;
+                    println!("ok start");
+                }
+                Err(_) => println!("error could not start recording"),
+            },
+            "pause" if !segments.recording() => println!("error not recording"),
+            "pause" => {
+                segments.stop();
+                println!("ok pause {:.3}", segments.elapsed().as_secs_f64());
+            }
+            "resume" if !started => println!("error not started"),
+            "resume" if segments.recording() => println!("error not paused"),
+            "resume" => match segments.start(&record, &Stopper::default()) {
+                Ok(()) => println!("ok resume"),
+                Err(_) => println!("error could not resume recording"),
+            },
+            "marker" if !started => println!("error not started"),
+            "marker" => {
+                let time = segments.elapsed();
+                let name = if argument.is_empty() {
+                    format!("Marker {}", markers.len() + 1)
+                } else {
+                    argument.to_owned()
+                };
+                println!("ok marker {:.3}", time.as_secs_f64());
+                markers.push((time, name));
+            }
+            "snapshot" => {
+                snapshots += 1;
+                // Snapshots are named after where the recording is saved.
+                let saved = saved_path(output).unwrap_or_else(|| output.to_owned());
+                let stem = saved.file_stem().unwrap_or_default().to_string_lossy();
+                let path = saved.with_file_name(format!("{}.snapshot-{}.png", stem, snapshots));
+                match snapshot(&path) {
+                    Ok(()) => println!("ok snapshot {}", path.display()),
+                    Err(_) => println!("error could not take snapshot"),
+                }
+            }
+            "stop" => break,
+            "" => {}
+            command => println!("error unknown command {:?}", command),
+        }
+    }
+
+    match segments.finish(output, format, &markers) {
+        Ok(true) => println!("ok stop"),
+        Ok(false) => println!("error nothing recorded"),
+        Err(e) => {
+            println!("error could not save recording");
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Wait for a recording to the output that can be paused and resumed from
+/// standard input, until it is stopped through the stopper.
+///
+/// Recording is resumed into segments that are joined onto the output once
+/// it is stopped.
+pub fn wait_pausable(
+    mut child: Child,
+    stopper: &Stopper,
+    output: &Path,
+    format: &str,
+    record: impl Fn(&Path) -> Command,
+) -> io::Result<()> {
+    let (sender, commands) = mpsc::channel();
+    thread::spawn(move || {
+        for line in stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    // The first part is recorded straight to the output.
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if let Ok("p") = commands
+            .recv_timeout(POLL_INTERVAL)
+            .as_deref()
+            .map(str::trim)
+        {
+            stopper.pause();
+            break child.wait()?;
+        }
+    };
+    check_status(status, "record the video")?;
+    if stopper.stopped() {
+        return Ok(());
+    }
+
+    let mut segments = Segments::new(&extension(output))?;
+    segments.prepend(output)?;
+    emit!("Paused recording, type r and Enter to resume");
+    while !stopper.stopped() {
+        match commands.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => match line.trim() {
+                "p" if segments.recording() => {
+                    segments.stop();
+                    emit!("Paused recording, type r and Enter to resume");
+                }
+                "r" if !segments.recording() => {
+                    segments.start(&record, stopper)?;
+                    emit!("Resumed recording");
+                }
+                _ => {}
+            },
+            Err(RecvTimeoutError::Timeout) => {}
+            // Nothing can resume a paused recording once input has ended.
+            Err(RecvTimeoutError::Disconnected) if !segments.recording() => break,
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+    segments.finish(output, format, &[])?;
+    Ok(())
+}
+
+/// The extension of segments recorded for an output.
+fn extension(output: &Path) -> String {
+    output.extension().map_or("mkv".into(), |extension| {
+        extension.to_string_lossy().into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapters_between_markers() {
+        let markers = [
+            (Duration::from_secs(0), "Intro".to_owned()),
+            (Duration::from_millis(1500), "Demo".to_owned()),
+        ];
+        assert_eq!(
+            chapters(&markers, Duration::from_secs(3)),
+            ";FFMETADATA1\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1500\ntitle=Intro\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=1500\nEND=3000\ntitle=Demo\n"
+        );
+    }
+
+    #[test]
+    fn chapter_titles_are_escaped() {
+        let markers = [(Duration::from_secs(1), "a=b; #c \\d\ne".to_owned())];
+        assert_eq!(
+            chapters(&markers, Duration::from_secs(2)),
+            ";FFMETADATA1\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=1000\nEND=2000\n\
+             title=a\\=b\\; \\#c \\\\d\\\ne\n"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Draw a cursor onto captures.
+
+use std::env::temp_dir;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::args::RateControl;
+use crate::exec;
+use crate::util::*;
+
+/// An arrow cursor with its hotspot in the top-left corner.
+const CURSOR: &[u8] = include_bytes!("../assets/cursor.png");
+
+/// Write the cursor to a temporary file so it can be used as an ffmpeg input.
+pub fn cursor_image() -> io::Result<PathBuf> {
+    let mut path = temp_dir();
+    path.push(format!("screencap-cursor.{}.png", process::id()));
+    fs::write(&path, CURSOR)?;
+    Ok(path)
+}
+
+/// Get the width and height of an image.
+pub fn image_dimensions(filename: &Path) -> Option<(u32, u32)> {
+    let dimensions = command_output(exec!(ffprobe
+        -v error
+        -select_streams ("v:0")
+        -show_entries ("stream=width,height")
+        -of ("csv=p=0:s=x")
+        (filename.display())
+    ))
+    .ok()?
+    .next()?;
+    let (width, height) = dimensions.split_once('x')?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+/// Draw the cursor onto an image with its hotspot at the given position.
+pub fn overlay_cursor(filename: &Path, x: u32, y: u32) -> io::Result<()> {
+    let cursor = cursor_image()?;
+    let name = capture_file_name(filename)?;
+    let mut output = filename.to_owned();
+    output.set_file_name(format!(".cursor.{}", name));
+
+    let status = exec!(
+        ffmpeg
+            - hide_banner
+            - y
+            - i(filename.display())
+            - i(cursor.display())
+            - filter_complex(format!("overlay={}:{}", x, y))(output.display())
+    )
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .map_err(running("ffmpeg"));
+    let _ = fs::remove_file(&cursor);
+
+    check_status(status?, "draw the cursor")?;
+    fs::rename(&output, filename)
+}
+
+/// Get the position of the cursor on the screen.
+pub fn cursor_position() -> Option<(i32, i32)> {
+    let mut x = None;
+    let mut y = None;
+    for line in command_output(exec!(xdotool getmouselocation --shell)).ok()? {
+        if let Some(value) = line.strip_prefix("X=") {
+            x = value.parse().ok();
+        } else if let Some(value) = line.strip_prefix("Y=") {
+            y = value.parse().ok();
+        }
+    }
+    Some((x?, y?))
+}
+
+/// A position of the cursor at some time in seconds into a recording.
+pub type CursorPosition = (f64, i32, i32);
+
+/// Tracks the position of the cursor while recording.
+pub struct CursorTracker {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<Vec<CursorPosition>>,
+}
+
+impl CursorTracker {
+    /// Start polling the cursor position relative to an origin.
+    pub fn start(origin: (i32, i32), interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            let start = Instant::now();
+            let mut positions: Vec<CursorPosition> = Vec::new();
+            while !stopped.load(Ordering::Relaxed) {
+                if let Some((x, y)) = cursor_position() {
+                    let (x, y) = (x - origin.0, y - origin.1);
+                    let moved = positions
+                        .last()
+                        .is_none_or(|&(_, last_x, last_y)| (last_x, last_y) != (x, y));
+                    if moved {
+                        positions.push((start.elapsed().as_secs_f64(), x, y));
+                    }
+                }
+                thread::sleep(interval);
+            }
+            positions
+        });
+
+        CursorTracker { stop, thread }
+    }
+
+    /// Stop tracking and get each change in the position of the cursor.
+    pub fn stop(self) -> Vec<CursorPosition> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.join().expect("Cursor tracking thread")
+    }
+}
+
+/// Draw the cursor onto a video at its tracked positions.
+///
+/// The video is re-encoded with the given format, encoder and quality while
+/// any audio is copied unchanged.
+pub fn overlay_cursor_track(
+    input: &Path,
+    output: &Path,
+    positions: &[CursorPosition],
+    format: &str,
+    encoder: &str,
+    rate: RateControl,
+    preset: &str,
+) -> io::Result<()> {
+    let cursor = cursor_image()?;
+    let mut commands = cursor.clone();
+    commands.set_extension("cmd");
+    let script: String = positions
+        .iter()
+        .map(|(time, x, y)| {
+            format!(
+                "{:.3} overlay@cursor x {}, overlay@cursor y {};\n",
+                time, x, y
+            )
+        })
+        .collect();
+    fs::write(&commands, script)?;
+
+    let (x, y) = positions.first().map_or((0, 0), |&(_, x, y)| (x, y));
+    let filter = format!(
+        "[0:v]sendcmd=f={}[v];[v][1:v]overlay@cursor=x={}:y={}[out]",
+        commands.display(),
+        x,
+        y
+    );
+
+    let mut command = exec!(ffmpeg
+        -hide_banner
+        -threads (num_cpus::get())
+        -y
+        -i (input.display())
+        -i (cursor.display())
+        -filter_complex (filter)
+        -f (format)
+            -map ("[out]") ("-c:v") (encoder) ("-preset:v") (preset)
+            -map ("0:a?") ("-c:a") copy
+    );
+    let status = command
+        .args(rate.args())
+        .args(faststart_args(format))
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(running("ffmpeg"));
+    let _ = fs::remove_file(&cursor);
+    let _ = fs::remove_file(&commands);
+
+    check_status(status?, "draw the cursor")
+}
@@ -0,0 +1,150 @@
+//! Check that the tools a capture needs are installed before capturing.
+
+use std::process::Stdio;
+
+use crate::args::{CaptureMode::*, Config, ScreenRegion::*, ScreenshotTool};
+use crate::exec;
+use crate::util::*;
+use crate::wayland::Backend;
+
+/// Check that every tool the capture would run is installed, getting what
+/// is missing if not.
+///
+/// Where any one of several tools will do, they are reported together.
+pub fn check_dependencies(config: &Config, backend: Backend) -> Result<(), Vec<String>> {
+    let mut needed: Vec<Vec<&'static str>> = vec![vec!["ffmpeg"]];
+    let mut need = |tools: &[&'static str]| needed.push(tools.to_vec());
+
+    match backend {
+        Backend::Wayland => {
+            match config.mode() {
+                Image => need(&["grim"]),
+                Video(_) => need(&["wf-recorder"]),
+                Gif(_) | Audio => {}
+            }
+            match config.region() {
+                Select => need(&["slurp"]),
+                Window | ActiveOutput => need(&["swaymsg"]),
+                Screen | AllScreens | Geometry { .. } => {}
+            }
+        }
+        Backend::X11 if matches!(config.mode(), Audio) => {}
+        Backend::X11 => {
+            if let Image = config.mode() {
+                if !config.safe_mode() && !config.native() {
+                    let tools: Vec<_> = match config.screenshot_tool() {
+                        Some(tool) => vec![tool.program()],
+                        None => ScreenshotTool::ALL
+                            .iter()
+                            .map(|tool| tool.program())
+                            .collect(),
+                    };
+                    need(&tools);
+                }
+            }
+            if !config.safe_mode() {
+                match config.region() {
+                    Screen if config.exclude_struts() => need(&["xprop"]),
+                    Screen if config.monitor().is_some() => need(&["xrandr"]),
+                    Screen => need(&["xdpyinfo"]),
+                    Window => {
+                        need(&["xwininfo"]);
+                        if config.window_name().is_some() {
+                            need(&["xdotool"]);
+                        } else {
+                            need(&["xprop"]);
+                        }
+                    }
+                    // The pointer is found instead when no window is focused.
+                    ActiveOutput => {
+                        need(&["xprop"]);
+                        need(&["xwininfo"]);
+                        need(&["xdotool"]);
+                        need(&["xrandr"]);
+                    }
+                    Select => need(&["slop"]),
+                    AllScreens => need(&["xwininfo"]),
+                    Geometry { .. } => {}
+                }
+                if config.offset_correct().is_some() {
+                    need(&["xdpyinfo"]);
+                }
+                if let (Video(_), false) = (config.mode(), config.tonemap()) {
+                    need(&["xrandr"]);
+                }
+            }
+            if config.concat_screens().is_some() {
+                need(&["xrandr"]);
+            }
+            if config.cursor_fallback() {
+                need(&["xdotool"]);
+            }
+            if config.stop_after_idle().is_some() {
+                need(&["xprintidle"]);
+            }
+        }
+    }
+
+    if config.clipboard() {
+        need(&["xclip"]);
+    }
+    if config.notify() {
+        need(&["notify-send"]);
+    }
+    if config.rclone_remote().is_some() {
+        need(&["rclone"]);
+    }
+    if config.desktop_audio() {
+        need(&["pactl"]);
+    }
+
+    let mut missing: Vec<String> = Vec::new();
+    for tools in needed {
+        if tools.iter().all(|tool| which(tool).is_none()) {
+            let tools = one_of(&tools);
+            if !missing.contains(&tools) {
+                missing.push(tools);
+            }
+        }
+    }
+
+    // ffmpeg records from PulseAudio itself, so only the server is needed.
+    let records_audio = match config.mode() {
+        Video(_) => !config.no_audio(),
+        Audio => true,
+        Image | Gif(_) => false,
+    };
+    if records_audio && !pulse_server_running() {
+        missing.push("a running PulseAudio server".to_owned());
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(missing)
+    }
+}
+
+/// Whether a PulseAudio server, or PipeWire standing in for one, is running.
+///
+/// Without `pactl` the server can't be asked, so it is assumed to be running.
+fn pulse_server_running() -> bool {
+    match which("pactl") {
+        Some(_) => exec!(pactl info)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success()),
+        None => true,
+    }
+}
+
+/// Describe a choice of tools, any one of which will do.
+fn one_of(tools: &[&str]) -> String {
+    match tools.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,51 @@
+//! Stop recording once the user is idle.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::exec;
+use crate::stop::Stopper;
+use crate::util::*;
+
+/// How often the idle time is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Get the time since the last keyboard or mouse input.
+pub fn idle_time() -> Option<Duration> {
+    let millis = command_output(exec!(xprintidle)).ok()?.next()?;
+    millis.trim().parse().ok().map(Duration::from_millis)
+}
+
+/// Watches for the user to be idle and stops ffmpeg when they are.
+pub struct IdleWatch {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<bool>,
+}
+
+impl IdleWatch {
+    /// Start watching for the user to be idle for the given time.
+    pub fn start(limit: Duration, ffmpeg: Stopper) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                if idle_time().is_some_and(|idle| idle >= limit) {
+                    return ffmpeg.stop();
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+            false
+        });
+
+        IdleWatch { stop, thread }
+    }
+
+    /// Stop watching and get whether ffmpeg was stopped because the user was
+    /// idle.
+    pub fn stop(self) -> bool {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.join().expect("Idle watching thread")
+    }
+}
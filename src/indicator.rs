@@ -0,0 +1,52 @@
+//! Show that a recording is in progress.
+
+use std::process::{Child, Command, Stdio};
+
+use crate::util::*;
+use crate::{eemit, exec};
+
+/// An indicator shown while recording, removed when dropped.
+///
+/// The indicator is a separate process so that it stays responsive however
+/// busy recording gets. It is in the same process group as screencap so it is
+/// also interrupted by Ctrl-C.
+pub struct Indicator(Child);
+
+impl Indicator {
+    /// Show the indicator using `yad` or `zenity`, whichever is installed.
+    pub fn show() -> Option<Self> {
+        let mut command = if which("yad").is_some() {
+            exec!(yad("--notification")("--image=media-record")(
+                "--text=Recording"
+            ))
+        } else if which("zenity").is_some() {
+            zenity_indicator()
+        } else {
+            eemit!("Warning: install yad or zenity to show a recording indicator");
+            return None;
+        };
+
+        let child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| eemit!("Warning: could not show recording indicator: {}", e))
+            .ok()?;
+        Some(Indicator(child))
+    }
+}
+
+impl Drop for Indicator {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// A small pulsing zenity window titled REC.
+fn zenity_indicator() -> Command {
+    exec!(zenity("--progress")("--pulsate")("--no-cancel")(
+        "--title=REC"
+    )("--text=Recording"))
+}
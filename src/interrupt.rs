@@ -0,0 +1,68 @@
+//! Stop recording cleanly when interrupted.
+//!
+//! ffmpeg is run in its own process group so that Ctrl-C in the terminal only
+//! interrupts screencap, which then asks ffmpeg to stop so that it finishes
+//! writing the capture.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::stop::Stopper;
+
+/// How often interrupts are checked for.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set by the signal handler, as little else is safe to do there.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn interrupted(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Watches for SIGINT and stops ffmpeg when it is received.
+pub struct InterruptWatch {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<bool>,
+}
+
+impl InterruptWatch {
+    /// Handle SIGINT by stopping ffmpeg until the watch is stopped.
+    pub fn start(ffmpeg: Stopper) -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        set_handler(interrupted as extern "C" fn(libc::c_int) as libc::sighandler_t);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                if INTERRUPTED.load(Ordering::SeqCst) {
+                    // Interrupting again kills screencap as usual.
+                    set_handler(libc::SIG_DFL);
+                    return ffmpeg.stop();
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+            false
+        });
+
+        InterruptWatch { stop, thread }
+    }
+
+    /// Stop watching and get whether ffmpeg was stopped by an interrupt.
+    pub fn stop(self) -> bool {
+        self.stop.store(true, Ordering::Relaxed);
+        let interrupted = self.thread.join().expect("Interrupt watching thread");
+        set_handler(libc::SIG_DFL);
+        interrupted
+    }
+}
+
+/// Set how SIGINT is handled.
+fn set_handler(handler: libc::sighandler_t) {
+    // SAFETY: the handler only stores to an atomic.
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+    }
+}
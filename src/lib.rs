@@ -0,0 +1,2695 @@
+//! Screen and video capture.
+//!
+//! The `screencap` command is a thin wrapper around [`run`]. Other programs
+//! can capture with [`capture_image`] and [`capture_video`], which use the
+//! command's defaults but report nothing and only fail with errors.
+
+mod args;
+mod audio;
+mod benchmark;
+mod compat;
+mod control;
+mod cursor;
+mod deps;
+mod idle;
+mod indicator;
+mod interrupt;
+mod log;
+mod native;
+mod progress;
+mod sheet;
+mod speed;
+mod state;
+mod stop;
+mod template;
+mod util;
+mod wayland;
+
+use std::env::var;
+use std::fs::{self, File};
+use std::io::{self, stdin, BufRead, BufReader, IsTerminal, Write};
+use std::os::fd::AsFd;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, Stdio};
+use std::slice;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use clap::ErrorKind;
+
+use self::args::*;
+use self::audio::*;
+use self::benchmark::*;
+use self::compat::*;
+use self::control::*;
+use self::cursor::*;
+use self::deps::*;
+use self::idle::*;
+use self::indicator::*;
+use self::interrupt::*;
+use self::log::*;
+use self::native::*;
+use self::progress::*;
+use self::sheet::*;
+use self::speed::*;
+use self::state::*;
+use self::stop::*;
+use self::template::*;
+use self::util::*;
+use self::wayland::*;
+
+pub use self::args::ScreenRegion;
+
+/// Options for recording video with [`capture_video`].
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Frames per second to record at
+    pub framerate: u64,
+    /// Seconds to record for, or until Enter is pressed
+    pub duration: Option<u64>,
+    /// Whether to record audio from the default source
+    pub audio: bool,
+    /// Whether to draw the cursor
+    pub cursor: bool,
+    /// The video encoder to record with instead of the preferred one
+    pub vcodec: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            framerate: 30,
+            duration: None,
+            audio: true,
+            cursor: true,
+            vcodec: None,
+        }
+    }
+}
+
+/// Capture an image of a region of the screen, saved in the format given by
+/// the extension of the path.
+///
+/// The config file is not read, and an existing file at the path is not
+/// replaced.
+pub fn capture_image(path: &Path, region: ScreenRegion) -> io::Result<()> {
+    library_error(Config::for_image(path, region).and_then(capture))
+}
+
+/// Record video of a region of the screen, saved in the container given by
+/// the extension of the path.
+///
+/// The config file is not read, and an existing file at the path is not
+/// replaced.
+pub fn capture_video(path: &Path, region: ScreenRegion, options: &Options) -> io::Result<()> {
+    library_error(Config::for_video(path, region, options).and_then(capture))
+}
+
+/// Report a failed capture as an I/O error to other programs.
+fn library_error(result: Result<(), clap::Error>) -> io::Result<()> {
+    result.map_err(|error| {
+        // clap prefixes its messages for printing.
+        let message = error.message.trim_start_matches("error: ");
+        io::Error::other(message.to_owned())
+    })
+}
+
+/// Capture as directed by the command line, exiting if that fails.
+pub fn run() {
+    // Errors are reported the same way as clap reports its own.
+    let result = Config::from_args().and_then(|config| {
+        set_verbosity(config.verbosity());
+        set_quiet(config.quiet());
+        if config.refresh_caps() {
+            refresh_capabilities();
+        }
+        capture(config)
+    });
+    if let Err(error) = result {
+        error.exit();
+    }
+}
+
+fn capture(config: Config) -> Result<(), clap::Error> {
+    use_ffmpeg(config.ffmpeg_path().map(Path::to_owned));
+    if config.startup_check() {
+        check_dependencies(&config, Backend::detect()).map_err(|missing| {
+            let message = format!("Missing what is needed to capture: {}", missing.join("; "));
+            clap::Error::with_description(&message, ErrorKind::Io)
+        })?;
+    }
+    let path = match config.output() {
+        Some(_) if config.to_stdout() => PathBuf::from("pipe:1"),
+        Some(output) => output.to_owned(),
+        None => filename(&config).map_err(io_error)?,
+    };
+
+    if config.probe_only() {
+        probe(&path, &config).map_err(io_error)?;
+        return Ok(());
+    }
+
+    if config.print_region() {
+        if Backend::detect() == Backend::Wayland {
+            return Err(clap::Error::with_description(
+                "Can only print the region on X11",
+                ErrorKind::ArgumentConflict,
+            ));
+        }
+        print_region(&config).map_err(io_error)?;
+        return Ok(());
+    }
+
+    if let Some(vcodec) = config.vcodec() {
+        if find_codec(
+            FFMPEGSupport::video_encoders().map_err(io_error)?,
+            &[vcodec],
+            FFMPEGSupport::encode,
+        )
+        .is_none()
+        {
+            let message = format!("ffmpeg cannot encode video with {:?}", vcodec);
+            return Err(clap::Error::with_description(
+                &message,
+                ErrorKind::InvalidValue,
+            ));
+        }
+    }
+
+    if let Some(container) = config.container() {
+        if find_codec(
+            FFMPEGSupport::formats().map_err(io_error)?,
+            &[container],
+            FFMPEGSupport::encode,
+        )
+        .is_none()
+        {
+            let message = format!("ffmpeg cannot write video in {:?}", container);
+            return Err(clap::Error::with_description(
+                &message,
+                ErrorKind::InvalidValue,
+            ));
+        }
+    }
+
+    if config.write_config() {
+        let path = write_default_config().map_err(io_error)?;
+        emit!("Config written to {:?}", path);
+        return Ok(());
+    }
+
+    if config.list_audio_sources() {
+        list_audio_sources().map_err(io_error)?;
+        return Ok(());
+    }
+
+    if config.list_codecs() {
+        list_codecs(config.vcodec()).map_err(io_error)?;
+        return Ok(());
+    }
+
+    if config.benchmark_encoders() {
+        let (resolution, _) = x11_capture_region(&config).map_err(io_error)?;
+        let framerate = match config.mode() {
+            Video(rate) | Gif(rate) => rate,
+            Image | Audio => 30,
+        };
+        benchmark_encoders(&resolution, framerate).map_err(io_error)?;
+        return Ok(());
+    }
+
+    if config.fps_probe() {
+        let region = x11_capture_region(&config).and_then(even_video_region);
+        let (resolution, input) = region.map_err(io_error)?;
+        let framerate = match config.mode() {
+            Video(rate) | Gif(rate) => rate,
+            Image | Audio => 30,
+        };
+        let video = VideoCodecs::find(config.vcodec(), false)
+            .map_err(io_error)?
+            .video;
+        probe_framerate(&resolution, &input, &video, framerate).map_err(io_error)?;
+        return Ok(());
+    }
+
+    if let Some((container, video, audio)) = config.check_compat() {
+        report_compat(container, video, audio);
+        return Ok(());
+    }
+
+    if config.webcam().is_some()
+        && find_codec(
+            FFMPEGSupport::formats().map_err(io_error)?,
+            &["v4l2"],
+            FFMPEGSupport::decode,
+        )
+        .is_none()
+    {
+        return Err(clap::Error::with_description(
+            "ffmpeg cannot record webcams without v4l2 support",
+            ErrorKind::InvalidValue,
+        ));
+    }
+
+    let backend = Backend::detect();
+    if backend == Backend::X11 && !matches!(config.mode(), Audio) {
+        // Fail before anything is run rather than when a tool can't connect.
+        x11_screen().map_err(io_error)?;
+    }
+    if backend == Backend::Wayland && config.window_name().is_some() {
+        return Err(clap::Error::with_description(
+            "Can only find windows by name on X11",
+            ErrorKind::ArgumentConflict,
+        ));
+    }
+    if backend == Backend::Wayland && config.webcam().is_some() {
+        return Err(clap::Error::with_description(
+            "Can only overlay a webcam on X11",
+            ErrorKind::ArgumentConflict,
+        ));
+    }
+    let wayland_capture = backend == Backend::Wayland && !matches!(config.mode(), Audio);
+    if config.dry_run() && (wayland_capture || matches!(config.mode(), Gif(_))) {
+        return Err(clap::Error::with_description(
+            "Can only print the command for audio, and X11 image and video capture",
+            ErrorKind::ArgumentConflict,
+        ));
+    }
+
+    // Wayland outputs are only known by name.
+    let wayland_output = match (backend, config.monitor()) {
+        (Backend::Wayland, Some(MonitorId::Name(name))) => Some(name.as_str()),
+        (Backend::Wayland, Some(MonitorId::Index(_))) => {
+            return Err(clap::Error::with_description(
+                "Monitors can only be chosen by name on Wayland",
+                ErrorKind::InvalidValue,
+            ));
+        }
+        _ => None,
+    };
+
+    // Messages go to standard error while standard output is kept for the
+    // video or the path of the capture.
+    let stdout = if config.to_stdout() || config.print_path() {
+        Some(redirect_stdout().map_err(io_error)?)
+    } else {
+        None
+    };
+    let (stream, mut path_output) = match stdout {
+        Some(stdout) if config.to_stdout() => (Some(stdout), None),
+        stdout => (None, stdout),
+    };
+
+    // Repeated captures are each named as they are taken.
+    let mut stream = stream;
+    let mut saved: Vec<PathBuf> = Vec::new();
+    for shot in 1..=config.repeat() {
+        let path = match config.output() {
+            _ if config.repeat() == 1 => path.clone(),
+            Some(output) => numbered_path(output, shot),
+            None => {
+                let path = filename(&config).map_err(io_error)?;
+                if saved.contains(&path) {
+                    numbered_path(&path, shot)
+                } else {
+                    path
+                }
+            }
+        };
+
+        // Tools fail in confusing ways when the directory doesn't exist, such
+        // as ~/Pictures/Screenshot on a fresh install.
+        if !config.to_stdout() && !config.dry_run() {
+            create_parent_dir(&path).map_err(io_error)?;
+        }
+        if !config.to_stdout() && !config.overwrite() && path.exists() {
+            let message = format!("{:?} already exists, use --overwrite to replace it", path);
+            return Err(clap::Error::with_description(
+                &message,
+                ErrorKind::InvalidValue,
+            ));
+        }
+
+        if shot == 1 {
+            countdown(config.delay());
+        } else {
+            thread::sleep(Duration::from_secs(config.interval()));
+        }
+
+        take_capture(
+            &path,
+            &config,
+            backend,
+            wayland_output,
+            stream.take(),
+            &mut path_output,
+        )?;
+        saved.push(path);
+    }
+
+    if config.repeat() > 1 && path_output.is_none() {
+        emit!("Captures saved to:");
+        for path in &saved {
+            emit!("  {:?}", path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Take a single capture to a path, then edit, report and share it.
+fn take_capture(
+    path: &Path,
+    config: &Config,
+    backend: Backend,
+    wayland_output: Option<&str>,
+    stream: Option<File>,
+    path_output: &mut Option<File>,
+) -> Result<(), clap::Error> {
+    // Window and active output captures are repeated with the geometry they
+    // had at the start of the capture.
+    let geometry = match (backend, config.region()) {
+        _ if matches!(config.mode(), Audio) => None,
+        (Backend::X11, Window | ActiveOutput) => {
+            Some(x11_capture_region(config).map_err(io_error)?)
+        }
+        _ => None,
+    };
+
+    // The pointer is checked against the size of the capture before capturing
+    // where the size is already known, which for a selection it isn't.
+    if let (Some(pointer), Backend::X11) = (config.pointer_at(), backend) {
+        if !matches!(config.region(), Select) {
+            let (resolution, _) = match &geometry {
+                Some(geometry) => geometry.clone(),
+                None => x11_capture_region(config).map_err(io_error)?,
+            };
+            check_pointer(pointer, x11_resolution(&resolution))?;
+        }
+    }
+
+    let screenshot_tool = match config.mode() {
+        Image if backend == Backend::X11 && !config.safe_mode() && !config.native() => {
+            Some(match config.screenshot_tool() {
+                Some(tool) => tool,
+                None => detect_screenshot_tool().map_err(io_error)?,
+            })
+        }
+        _ => None,
+    };
+    let cropped = crop_afterwards(config);
+
+    // Images are captured as PNG and converted to other formats afterwards,
+    // unless the screenshot tool can save the format and the image isn't
+    // edited.
+    let image_format = config.image_format();
+    let edited = cropped
+        || config.lut().is_some()
+        || config.watermark().is_some()
+        || config.pointer_at().is_some()
+        || config.scale().is_some();
+    let direct = !edited && screenshot_tool.is_some_and(|tool| tool.writes(image_format));
+    // Captures are written next to where they are saved and only moved into
+    // place once complete.
+    let partial = match stream {
+        None if !config.dry_run() => Some(PartialFile::new(path).map_err(io_error)?),
+        _ => None,
+    };
+    let output = partial.as_ref().map_or(path, PartialFile::path);
+
+    let capture = match config.mode() {
+        Image if image_format != ImageFormat::Png && !direct => {
+            if !FFMPEGSupport::video_encoders()
+                .map_err(io_error)?
+                .any(|codec| codec.has_name(image_format.encoder()))
+            {
+                let message = format!("ffmpeg cannot encode {} images", image_format.extension());
+                return Err(clap::Error::with_description(
+                    &message,
+                    ErrorKind::InvalidValue,
+                ));
+            }
+            let name = capture_file_name(path).map_err(io_error)?;
+            path.with_file_name(format!(".{}.png", name))
+        }
+        _ => output.to_owned(),
+    };
+
+    match config.mode() {
+        Image if backend == Backend::Wayland => {
+            capture_wayland_image(&capture, config.region(), wayland_output).map_err(io_error)?
+        }
+        Video(_) if backend == Backend::Wayland && stream.is_some() => {
+            return Err(clap::Error::with_description(
+                "Cannot write video to standard output on Wayland",
+                ErrorKind::ArgumentConflict,
+            ));
+        }
+        Video(rate) if backend == Backend::Wayland => {
+            capture_wayland_video(&capture, config.region(), wayland_output, rate)
+                .map_err(io_error)?
+        }
+        Image if config.safe_mode() => {
+            let region = x11_capture_region(config).map_err(io_error)?;
+            capture_frame(&capture, &region).map_err(io_error)?
+        }
+        Image if config.native() => {
+            let (resolution, region) = x11_capture_region(config).map_err(io_error)?;
+            capture_native(
+                &capture,
+                x11_region_origin(&region),
+                x11_resolution(&resolution),
+            )
+            .map_err(io_error)?;
+        }
+        Image => {
+            let tool = screenshot_tool.expect("Screenshot tool");
+            if cropped {
+                take_screenshot(
+                    &capture,
+                    Screen,
+                    tool,
+                    config.image_cursor(),
+                    config.dry_run(),
+                )
+                .map_err(io_error)?;
+                if config.dry_run() {
+                    return Ok(());
+                }
+                let (resolution, region) = x11_capture_region(config).map_err(io_error)?;
+                crop_image(
+                    &capture,
+                    x11_resolution(&resolution),
+                    x11_region_origin(&region),
+                )
+                .map_err(io_error)?;
+            } else {
+                take_screenshot(
+                    &capture,
+                    config.region(),
+                    tool,
+                    config.image_cursor(),
+                    config.dry_run(),
+                )
+                .map_err(io_error)?;
+            }
+        }
+        Gif(_) if backend == Backend::Wayland => {
+            return Err(clap::Error::with_description(
+                "Cannot capture GIFs on Wayland",
+                ErrorKind::ArgumentConflict,
+            ));
+        }
+        Video(rate) => record_video(&capture, config, rate, stream).map_err(io_error)?,
+        Gif(rate) => capture_gif(&capture, config, rate).map_err(io_error)?,
+        Audio => capture_audio(&capture, config).map_err(io_error)?,
+    }
+
+    if config.dry_run() {
+        return Ok(());
+    }
+
+    if let Image = config.mode() {
+        if let Some(lut) = config.lut() {
+            filter_image(&capture, &lut_filter(lut)).map_err(io_error)?;
+        }
+        if let Some(watermark) = config.watermark() {
+            filter_image(
+                &capture,
+                &watermark_filter(watermark, config.timestamp_format()),
+            )
+            .map_err(io_error)?;
+        }
+    }
+
+    if let Some((x, y)) = config.pointer_at() {
+        let size = image_dimensions(&capture)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Could not read the captured image size",
+                )
+            })
+            .map_err(io_error)?;
+        check_pointer((x, y), size)?;
+        overlay_cursor(&capture, x, y).map_err(io_error)?;
+    }
+
+    if let (Image, Some(scale)) = (config.mode(), config.scale()) {
+        filter_image(&capture, &format!("scale={}", scale)).map_err(io_error)?;
+    }
+
+    if capture != output {
+        convert_image(&capture, output, image_format, config.overwrite()).map_err(io_error)?;
+        fs::remove_file(&capture).map_err(io_error)?;
+    }
+    if let Some(partial) = partial {
+        partial.save().map_err(io_error)?;
+    }
+
+    if config.to_stdout() {
+        eemit!("Capture written to standard output");
+    } else if let Some(output) = path_output {
+        writeln!(output, "{}", path.display()).map_err(io_error)?;
+    } else if config.repeat() == 1 {
+        emit!("Capture saved to {:?}", path);
+    }
+    // Captures made as a library have no command line to repeat.
+    if !config.invocation().is_empty() {
+        save_last_run(config.invocation(), geometry.as_ref());
+    }
+
+    if config.notify() {
+        notify_saved(path, matches!(config.mode(), Image | Gif(_)));
+    }
+
+    if config.clipboard() {
+        let mime_type = match config.mode() {
+            Image => Some(image_format.mime_type()),
+            Gif(_) => Some("image/gif"),
+            Video(_) | Audio => None,
+        };
+        copy_to_clipboard(path, mime_type);
+    }
+
+    if let Some(sheet) = config.contact_sheet() {
+        let sheet = contact_sheet(path, sheet).map_err(io_error)?;
+        emit!("Contact sheet saved to {:?}", sheet);
+    }
+
+    if let Some(remote) = config.rclone_remote() {
+        rclone_copy(path, remote, config.rclone_link());
+    }
+
+    if let Some(command) = config.upload() {
+        upload(path, command);
+    }
+
+    Ok(())
+}
+
+/// Check that a pointer position is within a capture of the given size.
+fn check_pointer((x, y): (u32, u32), (width, height): (u32, u32)) -> Result<(), clap::Error> {
+    if x >= width || y >= height {
+        let message = format!(
+            "Pointer position {},{} is outside the {}x{} capture",
+            x, y, width, height
+        );
+        return Err(clap::Error::with_description(
+            &message,
+            ErrorKind::InvalidValue,
+        ));
+    }
+    Ok(())
+}
+
+/// Wait for a number of seconds, printing how many are left each second.
+fn countdown(seconds: u64) {
+    for remaining in (1..=seconds).rev() {
+        emit!("Capturing in {}...", remaining);
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+/// Point standard output at standard error, getting the original standard
+/// output to write the capture to.
+fn redirect_stdout() -> io::Result<File> {
+    io::stdout().flush()?;
+    let stream = io::stdout().as_fd().try_clone_to_owned()?;
+    // SAFETY: both descriptors are open for the life of the process.
+    if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(File::from(stream))
+}
+
+/// Report an error running a command the same way as clap reports its own.
+fn io_error(error: io::Error) -> clap::Error {
+    clap::Error::with_description(&error.to_string(), ErrorKind::Io)
+}
+
+/// Report what would be captured without capturing anything.
+fn probe(filename: &Path, config: &Config) -> io::Result<()> {
+    if let Audio = config.mode() {
+        return probe_audio(filename, config);
+    }
+
+    let region = config.region().name();
+    // A selection is only known once the user has made it.
+    let geometry = match config.region() {
+        Select => None,
+        _ => Some(x11_capture_region(config)?),
+    };
+    let (mode, framerate, codecs) = match config.mode() {
+        Image => ("image", None, None),
+        Video(rate) => {
+            let mut codecs = VideoCodecs::find(config.vcodec(), !config.no_audio())?;
+            if let Some(container) = config.container() {
+                codecs.format = container.to_owned();
+            }
+            ("video", Some(rate), Some(codecs))
+        }
+        Gif(rate) => ("gif", Some(rate), None),
+        Audio => unreachable!("Audio is probed separately"),
+    };
+    let audio_source = match codecs.as_ref().and_then(|c| c.pulse.as_ref()) {
+        Some(_) => {
+            Some(chosen_audio_source(config)?.unwrap_or_else(|| DEFAULT_AUDIO_SOURCE.to_owned()))
+        }
+        None => None,
+    };
+
+    if config.json() {
+        let report = json!({
+            "mode": mode,
+            "region": region,
+            "resolution": geometry.as_ref().map(|(resolution, _)| resolution),
+            "input": geometry.as_ref().map(|(_, input)| input),
+            "output": filename,
+            "framerate": framerate,
+            "duration": config.duration(),
+            "format": codecs.as_ref().map(|c| &c.format),
+            "video_encoder": codecs.as_ref().map(|c| &c.video),
+            "audio_encoder": codecs.as_ref().map(|c| &c.audio),
+            "audio_source": audio_source,
+        });
+        println!("{}", report);
+        return Ok(());
+    }
+
+    println!("Mode:          {}", mode);
+    match geometry {
+        Some((resolution, input)) => {
+            println!("Region:        {} ({} at {})", region, resolution, input)
+        }
+        None => println!("Region:        {} (chosen when capturing)", region),
+    }
+    println!("Output:        {}", filename.display());
+    if let Some(framerate) = framerate {
+        println!("Framerate:     {} fps", framerate);
+        match config.duration() {
+            Some(duration) => println!("Duration:      {} seconds", duration),
+            None => println!("Duration:      until stopped"),
+        }
+    }
+    if let Some(codecs) = codecs {
+        println!("Format:        {}", codecs.format);
+        println!("Video encoder: {}", codecs.video);
+        match (codecs.audio, codecs.pulse) {
+            (Some(audio), Some(pulse)) => {
+                println!("Audio encoder: {}", audio);
+                println!("Audio source:  {} ({})", audio_source.unwrap(), pulse);
+            }
+            _ => println!("Audio:         none"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the size and position of the region that would be captured as JSON.
+fn print_region(config: &Config) -> io::Result<()> {
+    let ((width, height), (x, y)) = x11_capture_geometry(config)?;
+    let region = json!({"width": width, "height": height, "x": x, "y": y});
+    println!("{}", region);
+    Ok(())
+}
+
+/// Report what audio would be recorded without recording anything.
+fn probe_audio(filename: &Path, config: &Config) -> io::Result<()> {
+    let (audio, _) = audio_file_codec()?;
+    let sources = audio_only_sources(config)?;
+
+    if config.json() {
+        let report = json!({
+            "mode": "audio",
+            "output": filename,
+            "duration": config.duration(),
+            "audio_encoder": audio,
+            "audio_sources": sources,
+        });
+        println!("{}", report);
+        return Ok(());
+    }
+
+    println!("Mode:          audio");
+    println!("Output:        {}", filename.display());
+    match config.duration() {
+        Some(duration) => println!("Duration:      {} seconds", duration),
+        None => println!("Duration:      until stopped"),
+    }
+    println!("Audio encoder: {}", audio);
+    println!("Audio source:  {}", sources.join(", "));
+
+    Ok(())
+}
+
+/// Print the formats and codecs ffmpeg would be used with to record video.
+///
+/// Unlike recording, nothing missing is treated as an error so that every
+/// category can be checked at once.
+fn list_codecs(vcodec: Option<&str>) -> io::Result<()> {
+    let video = find_codecs(
+        FFMPEGSupport::video_encoders()?,
+        vcodec.as_ref().map_or(VIDEO_ENCODERS, slice::from_ref),
+        FFMPEGSupport::encode,
+    );
+    let mut rows = vec![
+        (
+            "Format",
+            find_codec(FFMPEGSupport::formats()?, FORMATS, FFMPEGSupport::encode),
+        ),
+        (
+            "X11 input",
+            find_codec(
+                FFMPEGSupport::formats()?,
+                &["x11grab"],
+                FFMPEGSupport::decode,
+            ),
+        ),
+        (
+            "Pulse input",
+            find_codec(FFMPEGSupport::formats()?, &["pulse"], FFMPEGSupport::decode),
+        ),
+        (
+            "Audio encoder",
+            find_codec(
+                FFMPEGSupport::audio_encoders()?,
+                AUDIO_ENCODERS,
+                FFMPEGSupport::encode,
+            ),
+        ),
+        ("Video encoder", video.first().cloned()),
+    ];
+
+    // Fallbacks are listed one to a line under a single heading.
+    match &video[..] {
+        [] | [_] => rows.push(("Video fallbacks", Some("(none)".to_owned()))),
+        [_, fallbacks @ ..] => rows.extend(fallbacks.iter().enumerate().map(|(i, fallback)| {
+            let category = if i == 0 { "Video fallbacks" } else { "" };
+            (category, Some(fallback.clone()))
+        })),
+    }
+
+    for (category, codec) in &rows {
+        match codec {
+            Some(name) => {
+                let description = FFMPEGSupport::find(name)?
+                    .map(|codec| codec.description().to_owned())
+                    .unwrap_or_default();
+                println!("{:<16}{:<16}{}", category, name, description);
+            }
+            None => println!("{:<16}(not supported)", category),
+        }
+    }
+    Ok(())
+}
+
+/// Send a desktop notification that a capture was saved, showing it as the
+/// icon if it is an image.
+///
+/// Failures are reported as warnings as the capture is still saved.
+fn notify_saved(filename: &Path, thumbnail: bool) {
+    if which("notify-send").is_none() {
+        eemit!("Warning: notify-send not found, no notification sent");
+        return;
+    }
+
+    let path = fs::canonicalize(filename).unwrap_or_else(|_| filename.to_owned());
+    let mut command = exec!(("notify-send") - a screencap);
+    if thumbnail {
+        exec!(@(command) -i (path.display()));
+    }
+    exec!(@(command) ("Capture saved") (path.display()));
+
+    let sent = command
+        .stdin(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if !sent {
+        eemit!("Warning: failed to send a notification");
+    }
+}
+
+/// Copy a capture to the clipboard as the given type, or its path if none.
+///
+/// Failures are reported as warnings as the capture is still saved.
+fn copy_to_clipboard(filename: &Path, mime_type: Option<&str>) {
+    if which("xclip").is_none() {
+        eemit!("Warning: xclip not found, capture not copied to the clipboard");
+        return;
+    }
+
+    // xclip keeps running in the background to serve the clipboard.
+    let mut command = exec!(xclip - selection clipboard);
+    let copied = match mime_type {
+        Some(mime_type) => {
+            exec!(@(command) -t (mime_type) -i (filename.display()));
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .status()
+                .map(|status| status.success())
+        }
+        None => command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .and_then(|mut child| {
+                let path = fs::canonicalize(filename)?;
+                let mut input = child.stdin.take().expect("xclip standard input");
+                write!(input, "{}", path.display())?;
+                drop(input);
+                child.wait()
+            })
+            .map(|status| status.success()),
+    };
+
+    match copied {
+        Ok(true) => emit!("Capture copied to the clipboard"),
+        _ => eemit!("Warning: failed to copy capture to the clipboard"),
+    }
+}
+
+/// Copy a capture to an rclone remote.
+///
+/// Failures are reported as warnings as the capture is still saved locally.
+fn rclone_copy(filename: &Path, remote: &str, link: bool) {
+    if which("rclone").is_none() {
+        eemit!(
+            "Warning: rclone not found, capture not copied to {}",
+            remote
+        );
+        return;
+    }
+
+    let copied = exec!(rclone copy (filename.display()) (remote))
+        .stdin(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if !copied {
+        eemit!("Warning: failed to copy capture to {}", remote);
+        return;
+    }
+
+    emit!("Capture copied to {}", remote);
+
+    if link {
+        let name = match capture_file_name(filename) {
+            Ok(name) => name,
+            Err(e) => {
+                eemit!("Warning: rclone could not create a public link: {}", e);
+                return;
+            }
+        };
+        let remote = remote.trim_end_matches('/');
+        let separator = if remote.ends_with(':') { "" } else { "/" };
+        let target = format!("{}{}{}", remote, separator, name);
+        match command_output(exec!(rclone link (target)))
+            .ok()
+            .and_then(|mut lines| lines.next())
+        {
+            Some(url) => println!("Public link: {}", url),
+            None => eemit!("Warning: rclone could not create a public link"),
+        }
+    }
+}
+
+/// Run a command to upload a capture, printing what it outputs, such as a
+/// link to the upload.
+///
+/// Failures are reported as warnings as the capture is still saved locally.
+fn upload(filename: &Path, command: &str) {
+    let command = shell_substitute(command, &filename.to_string_lossy());
+    let output = exec!(sh - c(command))
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            print!("{}", String::from_utf8_lossy(&output.stdout));
+        }
+        Ok(output) => eemit!("Warning: upload command failed with {}", output.status),
+        Err(e) => eemit!("Warning: could not run upload command: {}", e),
+    }
+}
+
+/// Container formats to record to in order of preference.
+const FORMATS: &[&str] = &["matroska", "mp4"];
+
+/// Audio encoders in order of preference.
+const AUDIO_ENCODERS: &[&str] = &["aac", "libvo_aac"];
+
+/// Video encoders in order of preference.
+const VIDEO_ENCODERS: &[&str] = &["h264_nvenc", "h264_qsv", "libx264", "h264"];
+
+/// Codecs used to capture video.
+#[derive(Debug)]
+struct VideoCodecs {
+    format: String,
+    x11: String,
+    /// The audio input and encoder, which are none for video without audio.
+    pulse: Option<String>,
+    audio: Option<String>,
+    video: String,
+    /// Video encoders to fall back to in order of preference.
+    fallbacks: Vec<String>,
+}
+
+impl VideoCodecs {
+    /// Find the preferred codecs supported by ffmpeg.
+    ///
+    /// A chosen video encoder is used without any fallbacks, and audio codecs
+    /// are only looked for when recording audio.
+    fn find(vcodec: Option<&str>, record_audio: bool) -> io::Result<Self> {
+        let format = find_codec(FFMPEGSupport::formats()?, FORMATS, FFMPEGSupport::encode)
+            .ok_or_else(|| unsupported("ffmpeg cannot write matroska or mp4"))?;
+
+        let x11 = find_codec(
+            FFMPEGSupport::formats()?,
+            &["x11grab"],
+            FFMPEGSupport::decode,
+        )
+        .ok_or_else(|| unsupported("ffmpeg cannot capture X11"))?;
+
+        let (pulse, audio) = if record_audio {
+            let pulse = find_codec(FFMPEGSupport::formats()?, &["pulse"], FFMPEGSupport::decode)
+                .ok_or_else(|| unsupported("ffmpeg cannot record from PulseAudio"))?;
+
+            let audio = find_codec(
+                FFMPEGSupport::audio_encoders()?,
+                AUDIO_ENCODERS,
+                FFMPEGSupport::encode,
+            )
+            .ok_or_else(|| unsupported("ffmpeg cannot encode audio"))?;
+
+            (Some(pulse), Some(audio))
+        } else {
+            (None, None)
+        };
+
+        let mut fallbacks = find_codecs(
+            FFMPEGSupport::video_encoders()?,
+            vcodec.as_ref().map_or(VIDEO_ENCODERS, slice::from_ref),
+            FFMPEGSupport::encode,
+        );
+        if fallbacks.is_empty() {
+            return Err(unsupported("ffmpeg cannot encode video"));
+        }
+        let video = fallbacks.remove(0);
+
+        Ok(VideoCodecs {
+            format,
+            x11,
+            pulse,
+            audio,
+            video,
+            fallbacks,
+        })
+    }
+}
+
+/// An error for something ffmpeg was built without.
+fn unsupported(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, message)
+}
+
+/// Capture video of the screen, written to the stream instead if there is one.
+fn record_video(
+    filename: &Path,
+    config: &Config,
+    framerate: u64,
+    stream: Option<File>,
+) -> io::Result<()> {
+    // Frames faster than the display refreshes are only ever duplicates.
+    let refresh_rate = if config.safe_mode() {
+        None
+    } else {
+        x11_refresh_rate()
+    };
+    let refresh_rate = refresh_rate
+        .map(|rate| rate.round() as u64)
+        .filter(|&rate| rate > 0 && framerate > rate);
+    let framerate = match refresh_rate {
+        Some(rate) if config.limit_framerate() => {
+            emit!("Recording at {} fps, the refresh rate of the display", rate);
+            rate
+        }
+        Some(rate) => {
+            eemit!(
+                "Warning: {} fps is faster than the {} Hz display, frames will be \
+                 duplicated; use --limit-framerate to record at {} fps",
+                framerate,
+                rate,
+                rate
+            );
+            framerate
+        }
+        None => framerate,
+    };
+
+    let VideoCodecs {
+        format,
+        x11,
+        pulse,
+        audio,
+        video,
+        fallbacks,
+    } = VideoCodecs::find(config.vcodec(), !config.no_audio())?;
+    let format = config.container().map_or(format, str::to_owned);
+    debug!("Format: {:#?}", format);
+    debug!("X11: {:#?}", x11);
+    debug!("Pulseaudio: {:#?}", pulse);
+    debug!("Audio: {:#?}", audio);
+    debug!("Video: {:#?}", video);
+    if let Err(message) = check_compat(&format, &video, audio.as_deref()) {
+        eemit!("Warning: {}", message);
+    }
+
+    // Each screen being concatenated is grabbed as a separate input.
+    let inputs = match config.concat_screens() {
+        Some(screens) => x11_monitor_regions(screens)?,
+        None => vec![even_video_region(x11_capture_region(config)?)?],
+    };
+
+    // As a fallback for drivers where x11grab loses the cursor, the cursor is
+    // tracked while recording and drawn on afterwards.
+    let cursor_fallback = config.cursor_fallback();
+    let capture = if cursor_fallback {
+        let name = capture_file_name(filename)?;
+        filename.with_file_name(format!(".raw.{}", name))
+    } else {
+        filename.to_owned()
+    };
+
+    let (crf, preset) = if config.adaptive_quality() {
+        let pixels = inputs
+            .iter()
+            .map(|(resolution, _)| {
+                let (width, height) = x11_resolution(resolution);
+                width as u64 * height as u64
+            })
+            .sum();
+        adaptive_quality(pixels, framerate)
+    } else {
+        (16, "fast")
+    };
+    let rate = match config.bitrate() {
+        Some(bitrate) => RateControl::Bitrate(bitrate),
+        None => RateControl::Crf(config.crf().unwrap_or(crf)),
+    };
+    let preset = config.preset().unwrap_or(preset);
+    info!("Quality: {} with preset {:?}", rate, preset);
+
+    // Filters applied to the captured video in order.
+    let mut filters = Vec::new();
+    if config.tonemap() {
+        filters.push(TONEMAP_FILTER.to_owned());
+    } else if !config.safe_mode() {
+        for output in x11_hdr_outputs()? {
+            eemit!(
+                "Warning: {} appears to be in an HDR mode, use --tonemap if colours look wrong",
+                output
+            );
+        }
+    }
+    if let Some(lut) = config.lut() {
+        filters.push(lut_filter(lut));
+    }
+    if let Some(watermark) = config.watermark() {
+        filters.push(watermark_filter(watermark, config.timestamp_format()));
+    }
+    if let Some(scale) = config.scale() {
+        filters.push(format!("scale={}", scale));
+    }
+
+    // The output monitor is recorded as a second source and mixed into the
+    // first.
+    let audio_sources = match (&pulse, config.mix_audio()) {
+        (None, _) => vec![],
+        (Some(_), mix) => {
+            let source = chosen_audio_source(config)?;
+            let mut sources = vec![source.unwrap_or_else(|| DEFAULT_AUDIO_SOURCE.to_owned())];
+            if mix {
+                sources.push(OUTPUT_MONITOR_SOURCE.to_owned());
+            }
+            sources
+        }
+    };
+    let audio_graph = Some(audio_sources.len())
+        .filter(|&count| count > 1)
+        .map(|count| audio_mix_filter(inputs.len(), count));
+
+    // The webcam is the input after the audio sources.
+    let webcam = config.webcam();
+    let webcam_input = webcam.map(|webcam| (inputs.len() + audio_sources.len(), webcam.position));
+    let filter_graph = video_filter_graph(&inputs, webcam_input, &filters);
+
+    // Audio can be written to its own file alongside the video.
+    let split_audio = if config.split_av() {
+        let (audio, extension) = audio_file_codec()?;
+        let path = filename.with_extension(extension);
+        debug!("Split audio: {:#?}", audio);
+        Some((audio, path))
+    } else {
+        None
+    };
+    // Audio written next to a partial video is only saved along with it.
+    let audio_partial = split_audio
+        .as_ref()
+        .and_then(|(_, path)| saved_path(path))
+        .map(|saved| PartialFile::new(&saved))
+        .transpose()?;
+
+    // Every input is stamped from the same clock so they stay aligned.
+    let wallclock = config.input_sync().map(|sync| sync.wallclock() as u8);
+
+    let build_command = |encoder: &str, output: &Path| {
+        // Intermediate recordings are always ours to replace.
+        let overwrite = overwrite_arg(config.overwrite() || output != filename);
+        let mut command = exec!(ffmpeg - hide_banner - threads(num_cpus::get())(overwrite));
+        if let Some(ProgressFormat::Json) = config.progress() {
+            exec!(@(command) -progress ("pipe:1"));
+        }
+
+        for (resolution, region) in &inputs {
+            exec!(@(command)
+                -f (x11)
+                    -draw_mouse ((config.cursor() && !cursor_fallback) as u8)
+                    -framerate (framerate)
+                    -video_size (resolution)
+            );
+            if config.show_region() {
+                exec!(@(command) -show_region (1));
+            }
+            if let Some(wallclock) = wallclock {
+                exec!(@(command) -use_wallclock_as_timestamps (wallclock));
+            }
+            if let Some(duration) = config.duration() {
+                exec!(@(command) -t (duration));
+            }
+            if let Some(megabytes) = config.input_buffer() {
+                let bytes = megabytes * 1024 * 1024;
+                exec!(@(command)
+                    -thread_queue_size (input_buffer_frames(bytes, resolution))
+                    -rtbufsize (bytes)
+                );
+            }
+            exec!(@(command) -i (region));
+        }
+
+        for source in &audio_sources {
+            exec!(@(command) -f (pulse.as_ref().unwrap()));
+            if let Some(wallclock) = wallclock {
+                exec!(@(command) -use_wallclock_as_timestamps (wallclock));
+            }
+            if let Some(delay) = config.audio_delay() {
+                exec!(@(command) -itsoffset (format!("{}ms", delay)));
+            }
+            if let Some(duration) = config.duration() {
+                exec!(@(command) -t (duration));
+            }
+            exec!(@(command) -i (source));
+        }
+
+        if let Some(webcam) = webcam {
+            exec!(@(command) -f v4l2);
+            if let Some(wallclock) = wallclock {
+                exec!(@(command) -use_wallclock_as_timestamps (wallclock));
+            }
+            if let Some(duration) = config.duration() {
+                exec!(@(command) -t (duration));
+            }
+            exec!(@(command) -i (webcam.device.display()));
+        }
+
+        let graphs: Vec<&str> = filter_graph
+            .iter()
+            .chain(&audio_graph)
+            .map(String::as_str)
+            .collect();
+        if !graphs.is_empty() {
+            exec!(@(command) -filter_complex (graphs.join(";")));
+        }
+        // Output to standard output can't be rewritten to move the index.
+        if stream.is_none() {
+            command.args(faststart_args(&format));
+        }
+        command.args(rate.args());
+
+        let video_stream = if filter_graph.is_some() { "[v]" } else { "0:0" };
+        let audio_stream = match audio_graph {
+            Some(_) => "[a]".to_owned(),
+            None => format!("{}:0", inputs.len()),
+        };
+
+        match (&split_audio, &audio) {
+            (Some((split, path)), _) => {
+                exec!(@(command)
+                    -f (format)
+                        -map (video_stream) ("-c:v") (encoder) ("-preset:v") (preset)
+                    (output.to_str().expect("Filename as string"))
+                    -map (audio_stream) ("-c:a") (split) ("-b:a") ("256k")
+                    (path.to_str().expect("Filename as string"))
+                );
+            }
+            (None, Some(audio)) => {
+                exec!(@(command)
+                    -f (format)
+                        -map (video_stream) ("-c:v") (encoder) ("-preset:v") (preset)
+                        -map (audio_stream) ("-c:a") (audio) ("-b:a") ("256k")
+                    (output.to_str().expect("Filename as string"))
+                );
+            }
+            (None, None) => {
+                exec!(@(command)
+                    -f (format)
+                        -map (video_stream) ("-c:v") (encoder) ("-preset:v") (preset)
+                    (output.to_str().expect("Filename as string"))
+                );
+            }
+        }
+
+        command
+    };
+
+    if config.dry_run() {
+        println!("{}", shell_command(&build_command(&video, &capture)));
+        return Ok(());
+    }
+
+    if config.record_stdin_commands() {
+        let snapshot = |output: &Path| capture_frame(output, &inputs[0]);
+        record_with_commands(
+            filename,
+            &format,
+            |segment| build_command(&video, segment),
+            snapshot,
+        )?;
+        return Ok(());
+    }
+
+    // ffmpeg is stopped by sending it a `q` when the user presses Enter or
+    // Ctrl-C, or once they are idle.
+    let idle_limit = config.stop_after_idle().map(Duration::from_secs);
+    if idle_limit.is_some() {
+        idle_time().ok_or_else(|| io::Error::other("Could not read idle time with xprintidle"))?;
+    }
+    let enter_to_stop = config.duration_from_selection();
+    let watch_speed = config.watch_speed();
+
+    if enter_to_stop {
+        emit!("Press Enter to start recording");
+        stdin().read_line(&mut String::new())?;
+    }
+
+    // Recordings from the terminal can be paused, as long as what is recorded
+    // can be joined back together and nothing else reads standard input or
+    // follows a single ffmpeg.
+    let pausable = config.interactive()
+        && stdin().is_terminal()
+        && stream.is_none()
+        && !enter_to_stop
+        && !cursor_fallback
+        && !watch_speed
+        && split_audio.is_none()
+        && config.duration().is_none()
+        && config.progress().is_none();
+
+    // Hardware encoders can be listed by ffmpeg but still fail to start, so
+    // each is tried in turn until one starts.
+    let retry_encoder = config.retry_encoder();
+    let log = config.log().map(open_log).transpose()?;
+    let existed = capture.exists();
+    // Interrupts are handled from before ffmpeg is first started, as it is
+    // run in its own process group and would otherwise be left recording.
+    let stopper = Stopper::default();
+    let interrupt_watch = InterruptWatch::start(stopper.clone());
+    let mut encoders = Some(video).into_iter().chain(fallbacks).peekable();
+    let started = (|| loop {
+        let encoder = encoders.next().expect("Video encoder to try");
+        let retry = retry_encoder && encoders.peek().is_some();
+        let piped = retry || watch_speed;
+
+        // ffmpeg's output is read here when it is watched, in which case the
+        // lines read are copied to the log.
+        let log = log.as_ref().map(File::try_clone).transpose()?;
+        let stdout = match &stream {
+            Some(stream) => Stdio::from(stream.try_clone()?),
+            None if config.progress().is_some() => Stdio::piped(),
+            None => Stdio::null(),
+        };
+        let (stderr, log) = match log {
+            _ if piped => (Stdio::piped(), log),
+            Some(log) => (Stdio::from(log), None),
+            None => (Stdio::null(), None),
+        };
+
+        let mut child = build_command(&encoder, &capture)
+            .process_group(0)
+            .stdin(Stdio::piped())
+            .stdout(stdout)
+            .stderr(stderr)
+            .spawn()
+            .map_err(running("ffmpeg"))?;
+        stopper.attach(child.stdin.take().expect("ffmpeg standard input"));
+        let stderr = child.stderr.take().map(|stderr| stderr_lines(stderr, log));
+
+        if retry && encoder_failed(&mut child, stderr.as_ref().unwrap(), &stopper) {
+            eemit!(
+                "Video encoder {:?} failed to start, trying the next",
+                encoder
+            );
+            // The failed attempt may have started writing the output.
+            if !existed {
+                let _ = fs::remove_file(&capture);
+            }
+            continue;
+        }
+
+        return Ok((child, encoder, stderr));
+    })();
+    // The handler is reset before ffmpeg failing to start is reported.
+    let (mut child, video, stderr) = match started {
+        Ok(started) => started,
+        Err(e) => {
+            interrupt_watch.stop();
+            return Err(e);
+        }
+    };
+
+    info!("Started 'ffmpeg' with PID #{}", child.id());
+    if retry_encoder {
+        info!("Recording with video encoder {:?}", video);
+    }
+    if let Some(limit) = hardware_framerate_limit(&video).filter(|&limit| framerate > limit) {
+        eemit!(
+            "Warning: {} may not keep up with {} fps, record with -R {} or libx264 to \
+             avoid dropped frames",
+            video,
+            framerate,
+            limit
+        );
+    }
+
+    let progress = child.stdout.take().map(report_progress);
+    let speed_watch = stderr
+        .filter(|_| watch_speed)
+        .map(|lines| SpeedWatch::start(lines, framerate));
+
+    let idle_watch = idle_limit.map(|limit| IdleWatch::start(limit, stopper.clone()));
+
+    if enter_to_stop {
+        emit!("Recording... press Enter to stop");
+        let stopper = stopper.clone();
+        thread::spawn(move || {
+            if stdin().read_line(&mut String::new()).is_ok() && stopper.stop() {
+                emit!("Stopping recording");
+            }
+        });
+    }
+
+    if pausable {
+        emit!("Recording... type p and Enter to pause, r and Enter to resume");
+    }
+
+    let tracker = if cursor_fallback {
+        let interval = Duration::from_secs(1) / framerate.max(1) as u32;
+        let (_, region) = &inputs[0];
+        Some(CursorTracker::start(x11_region_origin(region), interval))
+    } else {
+        None
+    };
+
+    let indicator = if config.indicator() {
+        Indicator::show()
+    } else {
+        None
+    };
+
+    // ffmpeg exits successfully when asked to stop, so a failure means the
+    // recording is incomplete.
+    let waited = if pausable {
+        let record = |segment: &Path| build_command(&video, segment);
+        wait_pausable(child, &stopper, &capture, &format, record)
+    } else {
+        child
+            .wait()
+            .and_then(|status| check_status(status, "record the video"))
+    };
+    drop(indicator);
+    if interrupt_watch.stop() {
+        emit!("Stopped recording on interrupt");
+    }
+    if let Some(progress) = progress {
+        progress.join().expect("Progress reporting thread");
+    }
+
+    if let Some(speed) = speed_watch.and_then(SpeedWatch::stop) {
+        eemit!(
+            "Warning: encoding fell to {}x real time, record with -R {} or a faster \
+             encoder to avoid dropped frames",
+            speed,
+            suggested_framerate(framerate, speed)
+        );
+    }
+
+    if let Some(idle_watch) = idle_watch {
+        if idle_watch.stop() {
+            emit!(
+                "Stopped recording after {} seconds without input",
+                config.stop_after_idle().unwrap()
+            );
+        }
+    }
+
+    let positions = tracker.map(CursorTracker::stop);
+    waited?;
+
+    if let Some(positions) = positions {
+        info!("Drawing cursor at {} tracked positions", positions.len());
+        overlay_cursor_track(
+            &capture, filename, &positions, &format, &video, rate, preset,
+        )?;
+        fs::remove_file(&capture)?;
+    }
+
+    if let Some(partial) = audio_partial {
+        emit!("Audio saved to {:?}", partial.save()?);
+    }
+
+    Ok(())
+}
+
+/// Get the highest framerate a hardware video encoder can reliably sustain.
+///
+/// Software encoders are only limited by the CPU, which is watched with
+/// `--watch-speed` instead.
+fn hardware_framerate_limit(encoder: &str) -> Option<u64> {
+    const LIMITS: &[(&str, u64)] = &[("_nvenc", 120), ("_qsv", 60), ("_vaapi", 60)];
+    LIMITS
+        .iter()
+        .find(|(suffix, _)| encoder.ends_with(suffix))
+        .map(|(_, limit)| *limit)
+}
+
+/// Find an audio encoder for an audio file on its own and the extension for
+/// it.
+fn audio_file_codec() -> io::Result<(String, &'static str)> {
+    const EXTENSIONS: &[(&str, &str)] = &[
+        ("libopus", "opus"),
+        ("opus", "opus"),
+        ("aac", "m4a"),
+        ("libvo_aac", "m4a"),
+    ];
+
+    let names: Vec<_> = EXTENSIONS.iter().map(|(name, _)| *name).collect();
+    let audio = find_codec(
+        FFMPEGSupport::audio_encoders()?,
+        &names,
+        FFMPEGSupport::encode,
+    )
+    .ok_or_else(|| unsupported("ffmpeg cannot encode audio"))?;
+    let extension = EXTENSIONS
+        .iter()
+        .find(|(name, _)| *name == audio)
+        .map_or("mka", |(_, extension)| extension);
+
+    Ok((audio, extension))
+}
+
+/// Get how many raw frames of a resolution fit in an input buffer.
+///
+/// x11grab frames are 32 bits per pixel, so 256 MiB holds 32 frames at
+/// 1920x1080. ffmpeg's default queue of 8 frames is the least used.
+fn input_buffer_frames(bytes: u64, resolution: &str) -> u64 {
+    let (width, height) = x11_resolution(resolution);
+    (bytes / (width as u64 * height as u64 * 4)).max(8)
+}
+
+/// Choose the CRF and preset for a capture from its size and framerate.
+///
+/// | Pixels          | CRF | Preset    |
+/// |-----------------|-----|-----------|
+/// | up to 1280x720  | 16  | fast      |
+/// | up to 1920x1080 | 18  | fast      |
+/// | up to 2560x1440 | 20  | faster    |
+/// | larger          | 22  | veryfast  |
+///
+/// Above 30 fps the next faster preset is used so the encoder keeps up.
+fn adaptive_quality(pixels: u64, framerate: u64) -> (u32, &'static str) {
+    const PRESETS: &[&str] = &["fast", "faster", "veryfast", "superfast"];
+
+    let (crf, preset) = match pixels {
+        p if p <= 1280 * 720 => (16, 0),
+        p if p <= 1920 * 1080 => (18, 0),
+        p if p <= 2560 * 1440 => (20, 1),
+        _ => (22, 2),
+    };
+    let preset = if framerate > 30 { preset + 1 } else { preset };
+
+    (crf, PRESETS[preset])
+}
+
+/// How long to watch ffmpeg for an encoder that failed to start.
+const ENCODER_STARTUP: Duration = Duration::from_secs(3);
+
+/// Messages from ffmpeg that show an encoder failed to start.
+const ENCODER_ERRORS: &[&str] = &[
+    "Error initializing output stream",
+    "Error while opening encoder",
+    "Could not open encoder",
+    "OpenEncodeSessionEx failed",
+    "No capable devices found",
+    "Cannot load",
+];
+
+/// Create the directory a file is to be written in if it doesn't exist.
+fn create_parent_dir(path: &Path) -> io::Result<()> {
+    match path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        Some(parent) => fs::create_dir_all(parent).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not create directory {:?}: {}", parent, e),
+            )
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Create the file ffmpeg's output is written to, along with its directory.
+fn open_log(path: &Path) -> io::Result<File> {
+    create_parent_dir(path)?;
+    File::create(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not create log {:?}: {}", path, e)))
+}
+
+/// Read ffmpeg's standard error a line at a time on another thread.
+///
+/// Progress lines are ended with a carriage return rather than a newline, so
+/// are split out as separate lines. Lines are also written to the log, if any.
+fn stderr_lines(stderr: ChildStderr, mut log: Option<File>) -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for chunk in BufReader::new(stderr).split(b'\r').map_while(Result::ok) {
+            for line in String::from_utf8_lossy(&chunk).lines() {
+                if let Some(log) = &mut log {
+                    let _ = writeln!(log, "{}", line);
+                }
+                // Output is still read once nothing is listening to keep
+                // ffmpeg from blocking.
+                let _ = sender.send(line.to_owned());
+            }
+        }
+    });
+    receiver
+}
+
+/// Watch the start of ffmpeg's output for an encoder that failed to start.
+///
+/// If the encoder failed, ffmpeg is killed. Once ffmpeg has been asked to
+/// stop, the encoder is not counted as failed so that no other is tried.
+fn encoder_failed(child: &mut Child, receiver: &Receiver<String>, stopper: &Stopper) -> bool {
+    let deadline = Instant::now() + ENCODER_STARTUP;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(remaining) {
+            Ok(line) => {
+                if ENCODER_ERRORS.iter().any(|error| line.contains(error)) {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return !stopper.stopped();
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => return false,
+            Err(RecvTimeoutError::Disconnected) => {
+                let succeeded = child.wait().is_ok_and(|status| status.success());
+                return !succeeded && !stopper.stopped();
+            }
+        }
+    }
+}
+
+/// Filters that tone map HDR video to SDR.
+///
+/// This needs ffmpeg to be built with zimg for `zscale`.
+const TONEMAP_FILTER: &str = "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+     tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p";
+
+/// Build the filter graph for the video inputs, with the filtered video as
+/// its `[v]` output.
+///
+/// A single input that isn't filtered needs no graph.
+fn video_filter_graph(
+    inputs: &[(String, String)],
+    webcam: Option<(usize, WebcamPosition)>,
+    filters: &[String],
+) -> Option<String> {
+    let chain = if filters.is_empty() {
+        "null".to_owned()
+    } else {
+        filters.join(",")
+    };
+
+    let (mut graph, screen) = if inputs.len() > 1 {
+        (format!("{};", xstack_filter(inputs)), "[stacked]")
+    } else if webcam.is_some() || !filters.is_empty() {
+        (String::new(), "[0:v]")
+    } else {
+        return None;
+    };
+    match webcam {
+        Some((index, position)) => graph.push_str(&format!(
+            "{}[{}:v]overlay={}[screen];[screen]{}[v]",
+            screen,
+            index,
+            position.overlay(),
+            chain
+        )),
+        None => graph.push_str(&format!("{}{}[v]", screen, chain)),
+    }
+    Some(graph)
+}
+
+/// Build a filter that tiles each video input side by side.
+///
+/// Inputs shorter than the tallest are padded at the bottom and the width and
+/// height of each are padded to be even, as required by most encoders.
+fn xstack_filter(inputs: &[(String, String)]) -> String {
+    let sizes: Vec<(u32, u32)> = inputs
+        .iter()
+        .map(|(resolution, _)| {
+            let (width, height) = x11_resolution(resolution);
+            ((width + 1) & !1, (height + 1) & !1)
+        })
+        .collect();
+    let height = sizes.iter().map(|&(_, height)| height).max().unwrap_or(0);
+
+    let mut filter = String::new();
+    let mut layout = Vec::new();
+    let mut x = 0;
+    for (index, &(width, _)) in sizes.iter().enumerate() {
+        filter.push_str(&format!(
+            "[{}:v]pad={}:{}:0:0:black[s{}];",
+            index, width, height, index
+        ));
+        layout.push(format!("{}_0", x));
+        x += width;
+    }
+    for index in 0..sizes.len() {
+        filter.push_str(&format!("[s{}]", index));
+    }
+    filter.push_str(&format!(
+        "xstack=inputs={}:layout={}[stacked]",
+        sizes.len(),
+        layout.join("|")
+    ));
+    filter
+}
+
+/// Get the X11 reference for the capture region with any correction and
+/// offset applied.
+fn x11_capture_region(config: &Config) -> io::Result<(String, String)> {
+    if let Some(geometry) = config.geometry() {
+        return Ok(geometry.clone());
+    }
+
+    // Offsets for overscan are added on top of everything else, and may move
+    // the region off the screen.
+    let (resolution, region) = x11_corrected_region(config)?;
+    match config.offset() {
+        (0, 0) => Ok((resolution, region)),
+        (dx, dy) => {
+            let (x, y) = x11_region_origin(&region);
+            let region = format!("{}+{},{}", x11_screen()?, x + dx, y + dy);
+            Ok((resolution, region))
+        }
+    }
+}
+
+/// Get the X11 reference for the capture region with any correction applied.
+fn x11_corrected_region(config: &Config) -> io::Result<(String, String)> {
+    let (resolution, region) = match (config.region(), config.monitor()) {
+        (Screen | AllScreens, _) if config.safe_mode() => {
+            let (width, height) = native_screen_size()?;
+            (
+                format!("{}x{}", width, height),
+                format!("{}+0,0", x11_screen()?),
+            )
+        }
+        (Screen, _) if config.exclude_struts() => x11_workarea()?,
+        (Screen, Some(monitor)) => x11_monitor_regions(slice::from_ref(monitor))?.remove(0),
+        (Window, _) if config.no_shadow() => {
+            let window = x11_target_window(config)?;
+            let (resolution, region) = x11_window_region(&window)?;
+            x11_trim_shadow(&resolution, &region, &window)?
+        }
+        (Window, _) => x11_window_region(&x11_target_window(config)?)?,
+        (region, _) => x11_region_string(region)?,
+    };
+    let correction = match config.offset_correct() {
+        Some(correction) => correction,
+        None => return Ok((resolution, region)),
+    };
+
+    let (width, height) = x11_resolution(&resolution);
+    let (mut width, mut height) = (width as i32, height as i32);
+    let (mut x, mut y) = x11_region_origin(&region);
+    match (correction, config.region()) {
+        (OffsetCorrection::Offset(dx, dy), _) => {
+            x += dx;
+            y += dy;
+        }
+        // Windows are grabbed without the frame drawn by the window manager.
+        (OffsetCorrection::FrameExtents, Window) => {
+            let (left, right, top, bottom) =
+                x11_frame_extents(&x11_target_window(config)?, "_NET_FRAME_EXTENTS")?;
+            x -= left;
+            y -= top;
+            width += left + right;
+            height += top + bottom;
+        }
+        (OffsetCorrection::FrameExtents, _) => {}
+    }
+
+    let (screen_width, screen_height) = x11_resolution(&x11_fullscreen()?.0);
+    let (screen_width, screen_height) = (screen_width as i32, screen_height as i32);
+    if x < 0 || y < 0 || x + width > screen_width || y + height > screen_height {
+        let message = format!(
+            "Corrected region {}x{}+{}+{} is not within the {}x{} screen",
+            width, height, x, y, screen_width, screen_height
+        );
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    }
+
+    Ok((
+        format!("{}x{}", width, height),
+        format!("{}+{},{}", x11_screen()?, x, y),
+    ))
+}
+
+/// Get the size and position of the capture region as numbers.
+fn x11_capture_geometry(config: &Config) -> io::Result<((u32, u32), (i32, i32))> {
+    let (resolution, region) = x11_capture_region(config)?;
+    Ok((x11_resolution(&resolution), x11_region_origin(&region)))
+}
+
+/// Get the X11 reference for the capture region.
+fn x11_region_string(region: ScreenRegion) -> io::Result<(String, String)> {
+    match region {
+        Screen => x11_fullscreen(),
+        Window => x11_current_window(),
+        Select => x11_select_region(),
+        Geometry {
+            width,
+            height,
+            x,
+            y,
+        } => Ok((
+            format!("{}x{}", width, height),
+            format!("{}+{},{}", x11_screen()?, x, y),
+        )),
+        ActiveOutput => x11_active_output(),
+        AllScreens => x11_root_region(),
+    }
+}
+
+/// Get the X11 reference for a region selected with slop.
+fn x11_select_region() -> io::Result<(String, String)> {
+    if which("slop").is_none() {
+        let message = "slop is needed to select a region to record";
+        return Err(io::Error::new(io::ErrorKind::NotFound, message));
+    }
+
+    let selection = command_output(exec!(slop - f("%wx%h+%x+%y")))?.next();
+    let region = selection.and_then(|geometry| geometry.trim().parse().ok());
+    match region {
+        Some(region @ Geometry { .. }) => x11_region_string(region),
+        _ => Err(io::Error::other("No region was selected")),
+    }
+}
+
+/// Get the width and height of an X11 capture resolution.
+fn x11_resolution(resolution: &str) -> (u32, u32) {
+    resolution
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .expect("Resolution as WxH")
+}
+
+/// Round the size of a region to record down to be even, as most video
+/// encoders need, warning when it changes.
+///
+/// Tiny regions, such as those of minimised windows, can't be recorded.
+fn even_video_region((resolution, region): (String, String)) -> io::Result<(String, String)> {
+    let (width, height) = x11_resolution(&resolution);
+    if width < 2 || height < 2 {
+        let message = format!("Cannot record a {} region, the smallest is 2x2", resolution);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    }
+
+    let (even_width, even_height) = (width & !1, height & !1);
+    if (even_width, even_height) != (width, height) {
+        eemit!(
+            "Warning: recording {}x{} of the {} region, as video must be an even size",
+            even_width,
+            even_height,
+            resolution
+        );
+    }
+    Ok((format!("{}x{}", even_width, even_height), region))
+}
+
+/// Get the screen position of an X11 capture region.
+fn x11_region_origin(region: &str) -> (i32, i32) {
+    region
+        .rsplit('+')
+        .next()
+        .and_then(|offset| offset.split_once(','))
+        .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)))
+        .unwrap_or((0, 0))
+}
+
+/// A monitor attached to the X11 screen.
+#[derive(Debug, Clone)]
+struct Monitor {
+    name: String,
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+}
+
+/// Get the monitors attached to the X11 screen.
+fn x11_monitors() -> io::Result<Vec<Monitor>> {
+    Ok(command_output(exec!(xrandr - -listmonitors))?
+        .skip(1)
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let geometry = words.nth(2)?;
+            let name = words.last()?.to_owned();
+
+            // Geometry is given as W/mmxH/mm+X+Y.
+            let (width, rest) = geometry.split_once('x')?;
+            let width = width.split('/').next()?.parse().ok()?;
+            let mut parts = rest.split('+');
+            let height = parts.next()?.split('/').next()?.parse().ok()?;
+            let x = parts.next()?.parse().ok()?;
+            let y = parts.next()?.parse().ok()?;
+
+            Some(Monitor {
+                name,
+                width,
+                height,
+                x,
+                y,
+            })
+        })
+        .collect())
+}
+
+/// Get the regions for the given monitors, or for every monitor if none are
+/// given.
+fn x11_monitor_regions(ids: &[MonitorId]) -> io::Result<Vec<(String, String)>> {
+    let monitors = x11_monitors()?;
+    let selected: Vec<&Monitor> = if ids.is_empty() {
+        monitors.iter().collect()
+    } else {
+        ids.iter()
+            .map(|id| find_monitor(&monitors, id))
+            .collect::<io::Result<_>>()?
+    };
+
+    selected.into_iter().map(x11_monitor_region).collect()
+}
+
+/// Get the X11 reference for a monitor.
+fn x11_monitor_region(monitor: &Monitor) -> io::Result<(String, String)> {
+    debug!(
+        "Monitor: {} ({}x{})",
+        monitor.name, monitor.width, monitor.height
+    );
+    Ok((
+        format!("{}x{}", monitor.width, monitor.height),
+        format!("{}+{},{}", x11_screen()?, monitor.x, monitor.y),
+    ))
+}
+
+/// Get the region for the monitor with the focused window, or the pointer if
+/// no window is focused.
+fn x11_active_output() -> io::Result<(String, String)> {
+    let (x, y) = match x11_active_window()? {
+        Some(window_id) => {
+            let (resolution, region) = x11_window_region(&window_id)?;
+            let (width, height) = x11_resolution(&resolution);
+            let (x, y) = x11_region_origin(&region);
+            (x + width as i32 / 2, y + height as i32 / 2)
+        }
+        None => cursor_position().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "No window is focused and xdotool could not find the pointer",
+            )
+        })?,
+    };
+
+    let monitors = x11_monitors()?;
+    let monitor = monitors
+        .iter()
+        .find(|monitor| {
+            (monitor.x..monitor.x + monitor.width as i32).contains(&x)
+                && (monitor.y..monitor.y + monitor.height as i32).contains(&y)
+        })
+        .ok_or_else(|| {
+            let message = format!("No monitor contains {},{}", x, y);
+            io::Error::new(io::ErrorKind::NotFound, message)
+        })?;
+    x11_monitor_region(monitor)
+}
+
+/// Find a monitor by its index or output name.
+fn find_monitor<'m>(monitors: &'m [Monitor], id: &MonitorId) -> io::Result<&'m Monitor> {
+    let monitor = match id {
+        MonitorId::Index(index) => monitors.get(*index),
+        MonitorId::Name(name) => monitors.iter().find(|monitor| &monitor.name == name),
+    };
+    monitor.ok_or_else(|| {
+        let names: Vec<&str> = monitors.iter().map(|monitor| &monitor.name[..]).collect();
+        let message = format!(
+            "No monitor {} connected, available monitors are: {}",
+            id,
+            names.join(", ")
+        );
+        io::Error::new(io::ErrorKind::NotFound, message)
+    })
+}
+
+/// Get the outputs that appear to be in an HDR mode.
+///
+/// Outputs are taken to be HDR when their colorspace property is BT.2020.
+fn x11_hdr_outputs() -> io::Result<Vec<String>> {
+    let mut outputs = Vec::new();
+    let mut output = None;
+    for line in command_output(exec!(xrandr - -prop))? {
+        if !line.starts_with(char::is_whitespace) {
+            output = line.split_whitespace().next().map(str::to_owned);
+        } else if line.trim().starts_with("Colorspace:") && line.contains("BT2020") {
+            outputs.extend(output.take());
+        }
+    }
+    Ok(outputs)
+}
+
+/// Get the refresh rate of the primary output, or else the first connected
+/// output.
+///
+/// xrandr marks the current mode of each output with a `*` after its rate.
+fn x11_refresh_rate() -> Option<f64> {
+    let mut rates = Vec::new();
+    let mut primary = false;
+    for line in command_output(exec!(xrandr)).ok()? {
+        if !line.starts_with(char::is_whitespace) {
+            primary = line.split_whitespace().nth(2) == Some("primary");
+        } else if let Some(rate) = line.split_whitespace().find(|word| word.contains('*')) {
+            let rate = rate.trim_end_matches(['*', '+']).parse().ok()?;
+            rates.push((primary, rate));
+        }
+    }
+    rates
+        .iter()
+        .find(|(primary, _)| *primary)
+        .or_else(|| rates.first())
+        .map(|(_, rate)| *rate)
+}
+
+/// Add the program whose output couldn't be read to an error.
+fn reading(program: &str) -> impl Fn(io::Error) -> io::Error + '_ {
+    move |e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read {} output: {}", program, e),
+        )
+    }
+}
+
+/// Get the region for the full screen.
+fn x11_fullscreen() -> io::Result<(String, String)> {
+    let lines = command_output(exec!(xdpyinfo))?;
+    let (lines, _) =
+        get_line(lines, |line| line.contains("screen #0")).map_err(reading("xdpyinfo"))?;
+    let (_lines, dimensions) = get_nth_from_line(lines, |line| line.contains("dimensions:"), 1)
+        .map_err(reading("xdpyinfo"))?;
+
+    Ok((dimensions.to_owned(), format!("{}+0,0", x11_screen()?)))
+}
+
+/// Get the X11 reference for the whole root window.
+///
+/// Unlike the size of the first screen, this always covers every monitor.
+fn x11_root_region() -> io::Result<(String, String)> {
+    let lines = command_output(exec!(xwininfo - root))?;
+    let (lines, width) =
+        get_nth_from_line(lines, |line| line.contains("Width:"), 1).map_err(reading("xwininfo"))?;
+    let (_lines, height) = get_nth_from_line(lines, |line| line.contains("Height:"), 1)
+        .map_err(reading("xwininfo"))?;
+
+    Ok((
+        format!("{}x{}", width, height),
+        format!("{}+0,0", x11_screen()?),
+    ))
+}
+
+/// Get the region of the screen not reserved for panels.
+///
+/// Window managers without EWMH support don't give a work area, in which case
+/// the whole screen is used.
+fn x11_workarea() -> io::Result<(String, String)> {
+    let lines = command_output(exec!(xprop - root _NET_WORKAREA))?;
+    let line = get_line(lines, |line| line.starts_with("_NET_WORKAREA"))
+        .ok()
+        .map(|(_, line)| line);
+
+    // The work area is given as X, Y, width and height for each desktop.
+    let area: Option<Vec<u32>> = line.as_ref().and_then(|line| {
+        line.split_once('=')?
+            .1
+            .split(',')
+            .take(4)
+            .map(|value| value.trim().parse().ok())
+            .collect()
+    });
+    let (x, y, width, height) = match area.as_deref() {
+        Some(&[x, y, width, height]) => (x, y, width, height),
+        _ => {
+            eemit!("Warning: the window manager gives no work area, capturing the whole screen");
+            return x11_fullscreen();
+        }
+    };
+
+    Ok((
+        format!("{}x{}", width, height),
+        format!("{}+{},{}", x11_screen()?, x, y),
+    ))
+}
+
+/// Get the region for the current window.
+fn x11_current_window() -> io::Result<(String, String)> {
+    x11_window_region(&x11_window()?)
+}
+
+/// Get the X11 reference for a window.
+fn x11_window_region(window_id: &str) -> io::Result<(String, String)> {
+    let lines = command_output(exec!(xwininfo - id(window_id)))?;
+    let (lines, xpos) = get_nth_from_line(lines, |line| line.contains("Absolute upper-left X:"), 3)
+        .map_err(reading("xwininfo"))?;
+    let (lines, ypos) = get_nth_from_line(lines, |line| line.contains("Absolute upper-left Y:"), 3)
+        .map_err(reading("xwininfo"))?;
+    let (lines, width) =
+        get_nth_from_line(lines, |line| line.contains("Width:"), 1).map_err(reading("xwininfo"))?;
+    let (_lines, height) = get_nth_from_line(lines, |line| line.contains("Height:"), 1)
+        .map_err(reading("xwininfo"))?;
+
+    Ok((
+        format!("{}x{}", width, height),
+        format!("{}+{},{}", x11_screen()?, xpos, ypos),
+    ))
+}
+
+/// Get the ID of the current window.
+fn x11_window() -> io::Result<String> {
+    x11_active_window()?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No window is focused"))
+}
+
+/// Get the ID of the window to capture, which is either found by name or is
+/// the focused window.
+fn x11_target_window(config: &Config) -> io::Result<String> {
+    let name = match config.window_name() {
+        Some(name) => name,
+        None => return x11_window(),
+    };
+    if which("xdotool").is_none() {
+        let message = "xdotool is needed to find a window by name";
+        return Err(io::Error::new(io::ErrorKind::NotFound, message));
+    }
+    x11_window_by_name(name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No visible window is named {:?}", name),
+        )
+    })
+}
+
+/// Get the ID of a visible window with a matching title, or else class.
+fn x11_window_by_name(name: &str) -> Option<String> {
+    ["--name", "--class"].iter().find_map(|field| {
+        command_output(exec!(xdotool search ("--onlyvisible") (field) (name)))
+            .ok()?
+            .next()
+    })
+}
+
+/// Get the ID of the current window, if any window is focused.
+fn x11_active_window() -> io::Result<Option<String>> {
+    Ok(command_output(exec!(xprop - root _NET_ACTIVE_WINDOW))?
+        .find(|line| line.contains("_NET_ACTIVE_WINDOW"))
+        .and_then(|line| line.split_whitespace().nth(4).map(str::to_owned))
+        .filter(|window_id| window_id != "0x0"))
+}
+
+/// Get the size of a frame around a window from a window property.
+///
+/// `_NET_FRAME_EXTENTS` gives the frame the window manager draws around a
+/// window, and `_GTK_FRAME_EXTENTS` the shadow a window with client side
+/// decorations draws inside itself. The extents are given as left, right, top
+/// and bottom.
+fn x11_frame_extents(window_id: &str, property: &str) -> io::Result<(i32, i32, i32, i32)> {
+    let extents: Vec<i32> = command_output(exec!(xprop - id(window_id)(property)))?
+        .next()
+        .and_then(|line| Some(line.split_once('=')?.1.to_owned()))
+        .map(|values| {
+            values
+                .split(',')
+                .filter_map(|value| value.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(match extents[..] {
+        [left, right, top, bottom] => (left, right, top, bottom),
+        _ => (0, 0, 0, 0),
+    })
+}
+
+/// Remove the shadow a window with client side decorations draws around
+/// itself from its region.
+fn x11_trim_shadow(
+    resolution: &str,
+    region: &str,
+    window_id: &str,
+) -> io::Result<(String, String)> {
+    let (left, right, top, bottom) = x11_frame_extents(window_id, "_GTK_FRAME_EXTENTS")?;
+    let (width, height) = x11_resolution(resolution);
+    let (x, y) = x11_region_origin(region);
+
+    Ok((
+        format!(
+            "{}x{}",
+            width as i32 - left - right,
+            height as i32 - top - bottom
+        ),
+        format!("{}+{},{}", x11_screen()?, x + left, y + top),
+    ))
+}
+
+/// Get the current screen.
+fn x11_screen() -> io::Result<String> {
+    match var("DISPLAY") {
+        Ok(display) if !display.is_empty() => Ok(format!("{}.0", display)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No X11 display found; set DISPLAY, or WAYLAND_DISPLAY to capture on Wayland",
+        )),
+    }
+}
+
+/// Capture an image of the screen with a screenshot tool.
+fn take_screenshot(
+    filename: &Path,
+    region: ScreenRegion,
+    tool: ScreenshotTool,
+    cursor: bool,
+    dry_run: bool,
+) -> io::Result<()> {
+    let filename = filename.to_str().expect("Filename as string");
+    let window = match (tool, region) {
+        (ScreenshotTool::Maim | ScreenshotTool::Import, Window) => Some(x11_window()?),
+        _ => None,
+    };
+    let format = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(|extension| extension.parse().ok())
+        .unwrap_or_default();
+    let mut command = exec!((tool.program()));
+    command
+        .args(tool.cursor_args(cursor))
+        .args(tool.format_args(format))
+        .args(tool.region_args(region, window.as_deref(), filename));
+    if dry_run {
+        println!("{}", shell_command(&command));
+        return Ok(());
+    }
+
+    let status = command.status().map_err(|e| {
+        io::Error::new(e.kind(), format!("Could not run {}: {}", tool.program(), e))
+    })?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed to take a screenshot",
+            tool.program()
+        )));
+    }
+    Ok(())
+}
+
+/// Whether the image is cropped out of a capture of the whole screen
+/// afterwards, as is done for regions that are adjusted.
+fn crop_afterwards(config: &Config) -> bool {
+    let adjusted = config.exclude_struts() || config.monitor().is_some();
+    match config.region() {
+        _ if config.geometry().is_some() => true,
+        // Screenshot tools can't correct the region themselves.
+        _ if config.offset_correct().is_some() => true,
+        Screen => adjusted,
+        ActiveOutput => true,
+        AllScreens => false,
+        // Screenshot tools can only capture the focused window themselves.
+        Window => config.no_shadow() || config.window_name().is_some(),
+        Select => false,
+        Geometry { .. } => true,
+    }
+}
+
+/// Find the first screenshot tool that is installed.
+fn detect_screenshot_tool() -> io::Result<ScreenshotTool> {
+    ScreenshotTool::ALL
+        .iter()
+        .copied()
+        .find(|tool| which(tool.program()).is_some())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "No screenshot tool found, install gnome-screenshot, scrot, maim or import",
+            )
+        })
+}
+
+/// Capture a single frame of a region with ffmpeg.
+fn capture_frame(filename: &Path, (resolution, region): &(String, String)) -> io::Result<()> {
+    let mut command = exec!(ffmpeg - hide_banner - y);
+    exec!(@(command)
+        -f x11grab -video_size (resolution) -i (region)
+        ("-frames:v") (1) (filename.display())
+    );
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(running("ffmpeg"))?;
+    check_status(status, "capture a frame")
+}
+
+/// Record an animated GIF of a region.
+///
+/// The region is recorded losslessly and then converted in two passes, the
+/// first finding the best palette for the recording and the second using it.
+/// Recording stops after the duration or when Enter is pressed.
+fn capture_gif(filename: &Path, config: &Config, framerate: u64) -> io::Result<()> {
+    let (resolution, region) = x11_capture_region(config)?;
+    let name = capture_file_name(filename)?;
+    let recording = filename.with_file_name(format!(".raw.{}.mkv", name));
+    let palette = filename.with_file_name(format!(".palette.{}.png", name));
+
+    let mut command = exec!(ffmpeg - hide_banner - y);
+    exec!(@(command)
+        -f x11grab -draw_mouse (config.cursor() as u8)
+            -framerate (framerate) -video_size (resolution)
+    );
+    if let Some(duration) = config.duration() {
+        exec!(@(command) -t (duration));
+    }
+    exec!(@(command)
+        -i (region)
+        ("-c:v") libx264 ("-preset:v") ultrafast -crf (0)
+        (recording.display())
+    );
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(running("ffmpeg"))?;
+    let stopper = child.stdin.take().map(Stopper::new);
+    if let (Some(stopper), None) = (stopper, config.duration()) {
+        emit!("Recording... press Enter to stop");
+        thread::spawn(move || {
+            if stdin().read_line(&mut String::new()).is_ok() {
+                stopper.stop();
+            }
+        });
+    }
+    check_status(child.wait()?, "record the GIF")?;
+
+    let passes = [
+        exec!(
+            ffmpeg - hide_banner - y - i(recording.display()) - vf("palettegen")(palette.display())
+        ),
+        exec!(
+            ffmpeg
+                - hide_banner(overwrite_arg(config.overwrite()))
+                - i(recording.display())
+                - i(palette.display())
+                - lavfi("paletteuse")(filename.display())
+        ),
+    ];
+    for mut pass in passes {
+        let status = pass
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(running("ffmpeg"))?;
+        check_status(status, "convert the recording to a GIF")?;
+    }
+
+    fs::remove_file(&recording)?;
+    fs::remove_file(&palette)?;
+    Ok(())
+}
+
+/// Record audio without video until the duration is up or Enter is pressed.
+fn capture_audio(filename: &Path, config: &Config) -> io::Result<()> {
+    let pulse = find_codec(FFMPEGSupport::formats()?, &["pulse"], FFMPEGSupport::decode)
+        .ok_or_else(|| unsupported("ffmpeg cannot record from PulseAudio"))?;
+    let (audio, _) = audio_file_codec()?;
+    debug!("Pulseaudio: {:#?}", pulse);
+    debug!("Audio: {:#?}", audio);
+
+    let sources = audio_only_sources(config)?;
+
+    let mut command = exec!(ffmpeg - hide_banner(overwrite_arg(config.overwrite())));
+    for source in &sources {
+        exec!(@(command) -f (pulse));
+        if let Some(delay) = config.audio_delay() {
+            exec!(@(command) -itsoffset (format!("{}ms", delay)));
+        }
+        if let Some(duration) = config.duration() {
+            exec!(@(command) -t (duration));
+        }
+        exec!(@(command) -i (source));
+    }
+    if sources.len() > 1 {
+        exec!(@(command)
+            -filter_complex (audio_mix_filter(0, sources.len())) -map ("[a]")
+        );
+    }
+    exec!(@(command) ("-c:a") (audio) ("-b:a") ("256k") (filename.display()));
+
+    if config.dry_run() {
+        println!("{}", shell_command(&command));
+        return Ok(());
+    }
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(running("ffmpeg"))?;
+    info!("Started 'ffmpeg' with PID #{}", child.id());
+    let stopper = child.stdin.take().map(Stopper::new);
+    let interrupt_watch = stopper.clone().map(InterruptWatch::start);
+    if let (Some(stopper), None) = (stopper, config.duration()) {
+        emit!("Recording... press Enter to stop");
+        thread::spawn(move || {
+            if stdin().read_line(&mut String::new()).is_ok() {
+                stopper.stop();
+            }
+        });
+    }
+    let status = child.wait();
+    if interrupt_watch.is_some_and(InterruptWatch::stop) {
+        emit!("Stopped recording on interrupt");
+    }
+    check_status(status?, "record audio")
+}
+
+/// Get the pulseaudio sources to record audio from without video.
+///
+/// What is playing is recorded unless another source is chosen, with both
+/// mixed together if asked to.
+fn audio_only_sources(config: &Config) -> io::Result<Vec<String>> {
+    let source = chosen_audio_source(config)?;
+    Ok(match (source, config.mix_audio()) {
+        (source, true) => vec![
+            source.unwrap_or_else(|| DEFAULT_AUDIO_SOURCE.to_owned()),
+            OUTPUT_MONITOR_SOURCE.to_owned(),
+        ],
+        (Some(source), false) => vec![source],
+        (None, false) => vec![OUTPUT_MONITOR_SOURCE.to_owned()],
+    })
+}
+
+/// Get the pulseaudio source chosen to record from, if any.
+///
+/// Recording desktop audio records from the monitor of the default sink.
+fn chosen_audio_source(config: &Config) -> io::Result<Option<String>> {
+    match config.audio_source() {
+        Some(source) => Ok(Some(source.to_owned())),
+        None if config.desktop_audio() => default_monitor_source().map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Crop a captured image to an area.
+fn crop_image(filename: &Path, (width, height): (u32, u32), (x, y): (i32, i32)) -> io::Result<()> {
+    let crop = format!("crop={}:{}:{}:{}", width, height, x, y);
+    filter_image(filename, &crop)
+}
+
+/// Run a captured image through an ffmpeg filter.
+fn filter_image(filename: &Path, filter: &str) -> io::Result<()> {
+    let name = capture_file_name(filename)?;
+    let output = filename.with_file_name(format!(".filter.{}", name));
+
+    let status =
+        exec!(ffmpeg - hide_banner - y - i(filename.display()) - vf(filter)(output.display()))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(running("ffmpeg"))?;
+    check_status(status, &format!("filter the image with {}", filter))?;
+    fs::rename(&output, filename)
+}
+
+/// Build a filter that grades video with a 3D LUT file.
+fn lut_filter(lut: &Path) -> String {
+    format!("lut3d=file={}", filter_escape(&lut.display().to_string()))
+}
+
+/// Build a filter that draws a watermark.
+///
+/// Placeholders in the text are expanded when the filter is built.
+fn watermark_filter(watermark: &Watermark, date_format: &str) -> String {
+    const MARGIN: u32 = 16;
+
+    let (x, y) = match watermark.position {
+        WatermarkPosition::TopLeft => (format!("{}", MARGIN), format!("{}", MARGIN)),
+        WatermarkPosition::TopRight => (format!("w-tw-{}", MARGIN), format!("{}", MARGIN)),
+        WatermarkPosition::BottomLeft => (format!("{}", MARGIN), format!("h-th-{}", MARGIN)),
+        WatermarkPosition::BottomRight => (format!("w-tw-{}", MARGIN), format!("h-th-{}", MARGIN)),
+        WatermarkPosition::Center => ("(w-tw)/2".to_owned(), "(h-th)/2".to_owned()),
+    };
+
+    let mut filter = format!(
+        "drawtext=text={}:expansion=none:fontsize={}:fontcolor=white@{}:\
+         box=1:boxcolor=black@{}:boxborderw=8:x={}:y={}",
+        filter_escape(&expand_template(&watermark.text, date_format)),
+        watermark.size,
+        watermark.opacity,
+        watermark.opacity / 2.0,
+        x,
+        y
+    );
+    match &watermark.font {
+        Some(font) => filter.push_str(&format!(
+            ":fontfile={}",
+            filter_escape(&font.display().to_string())
+        )),
+        None if which("fc-match").is_none() => {
+            eemit!("Warning: fontconfig not found, use --watermark-font to choose a font")
+        }
+        None => {}
+    }
+    filter
+}
+
+/// Escape a value for use as a filter option in a filter graph.
+///
+/// Values are escaped once to be read as an option, then again to be read as
+/// part of the graph.
+fn filter_escape(value: &str) -> String {
+    let escape = |value: &str, special: &[char]| {
+        let mut escaped = String::new();
+        for c in value.chars() {
+            if c == '\\' || special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    let option = escape(value, &['\'', ':']);
+    escape(&option, &['\'', '[', ']', ',', ';'])
+}
+
+/// Convert a captured image to another format.
+fn convert_image(
+    input: &Path,
+    output: &Path,
+    format: ImageFormat,
+    overwrite: bool,
+) -> io::Result<()> {
+    let mut command = exec!(ffmpeg - hide_banner(overwrite_arg(overwrite)) - i(input.display()));
+    exec!(@(command) ("-c:v") (format.encoder()));
+    if format == ImageFormat::Jpg {
+        exec!(@(command) ("-q:v") (2));
+    }
+    exec!(@(command) (output.display()));
+
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(running("ffmpeg"))?;
+    check_status(
+        status,
+        &format!("convert the image to {}", format.extension()),
+    )
+}
+
+/// Determine the name of the file given the capture mode.
+///
+/// The file name is expanded from the template, which by default is based on
+/// the host and the current date and time.
+///
+/// Videos are stored in ~/Videos/Screenshot and are saved in the chosen
+/// container, which is Matroska by default.
+/// Images are stores in ~/Pictures/Screenshot and are saved in the chosen
+/// image format, which is PNG by default.
+/// Audio is stored in ~/Music/Screenshot and is saved with the extension for
+/// its encoder.
+fn filename(config: &Config) -> io::Result<PathBuf> {
+    let home = var("HOME")
+        .map_err(|_| io::Error::new(io::ErrorKind::NotFound, "No HOME to save captures in"))?;
+    let (subdir, extension) = match config.mode() {
+        Image => ("Pictures", config.image_format().extension()),
+        Video(_) => ("Videos", config.video_extension()),
+        Gif(_) => ("Pictures", "gif"),
+        Audio => ("Music", audio_file_codec()?.1),
+    };
+    let template = expand_template(config.name_template(), config.timestamp_format());
+    let filename = format!("{}.{}", template, extension);
+
+    let mut path = Path::new(&home).to_owned();
+    path.push(subdir);
+    path.push("Screenshot");
+    path.push(filename);
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adaptive_quality_table() {
+        let cases = [
+            (640 * 480, 30, (16, "fast")),
+            (1280 * 720, 30, (16, "fast")),
+            (1280 * 720 + 1, 30, (18, "fast")),
+            (1920 * 1080, 30, (18, "fast")),
+            (1920 * 1080 + 1, 30, (20, "faster")),
+            (2560 * 1440, 30, (20, "faster")),
+            (2560 * 1440 + 1, 30, (22, "veryfast")),
+            (3840 * 2160, 30, (22, "veryfast")),
+            (1280 * 720, 31, (16, "faster")),
+            (1920 * 1080, 60, (18, "faster")),
+            (2560 * 1440, 60, (20, "veryfast")),
+            (3840 * 2160, 60, (22, "superfast")),
+        ];
+        for (pixels, framerate, quality) in cases {
+            assert_eq!(
+                adaptive_quality(pixels, framerate),
+                quality,
+                "{} pixels at {} fps",
+                pixels,
+                framerate
+            );
+        }
+    }
+
+    #[test]
+    fn input_buffer_frames_table() {
+        const MIB: u64 = 1024 * 1024;
+        let cases = [
+            (256 * MIB, "1920x1080", 32),
+            (64 * MIB, "1920x1080", 8),
+            (1024 * MIB, "3840x2160", 32),
+            (256 * MIB, "1280x720", 72),
+            (16 * MIB, "1920x1080", 8),
+            (0, "1920x1080", 8),
+        ];
+        for (bytes, resolution, frames) in cases {
+            assert_eq!(
+                input_buffer_frames(bytes, resolution),
+                frames,
+                "{} bytes at {}",
+                bytes,
+                resolution
+            );
+        }
+    }
+
+    #[test]
+    fn filter_escape_option_separators() {
+        assert_eq!(filter_escape("a:b"), r"a\\:b");
+        assert_eq!(filter_escape("it's"), r"it\\\'s");
+        assert_eq!(filter_escape(r"C:\lut"), r"C\\:\\\\lut");
+    }
+
+    #[test]
+    fn filter_escape_graph_separators() {
+        assert_eq!(filter_escape("a,b;c"), r"a\,b\;c");
+        assert_eq!(filter_escape("[v]"), r"\[v\]");
+    }
+
+    #[test]
+    fn filter_escape_leaves_percent() {
+        assert_eq!(filter_escape("50% off"), "50% off");
+    }
+
+    #[test]
+    fn watermark_filter_escapes_text() {
+        let watermark = Watermark {
+            text: "50% off: it's".to_owned(),
+            position: WatermarkPosition::BottomRight,
+            opacity: 0.5,
+            size: 24,
+            font: Some(PathBuf::from("/fonts/a:b.ttf")),
+        };
+        assert_eq!(
+            watermark_filter(&watermark, "%Y"),
+            concat!(
+                r"drawtext=text=50% off\\: it\\\'s:expansion=none:fontsize=24:",
+                "fontcolor=white@0.5:box=1:boxcolor=black@0.25:boxborderw=8:",
+                r"x=w-tw-16:y=h-th-16:fontfile=/fonts/a\\:b.ttf"
+            )
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Report what screencap is doing in more detail the more verbose it is.
+//!
+//! Messages are written to standard error so that they don't mix with the
+//! output of `--print-path` or video written to standard output.
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// How much detail to report, from the number of times `-v` is given.
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+/// Whether to report nothing but errors, which the command sets from
+/// `--quiet` so that captures made as a library report nothing.
+static QUIET: AtomicBool = AtomicBool::new(true);
+
+/// How much detail a message gives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// What is being done, reported with `-v`.
+    Info = 1,
+    /// The codecs and inputs chosen, reported with `-vv`.
+    Debug = 2,
+}
+
+/// Set how much detail to report.
+pub fn set_verbosity(verbosity: u64) {
+    VERBOSITY.store(verbosity.min(Level::Debug as u64) as u8, Ordering::Relaxed);
+}
+
+/// Whether messages with a level of detail are reported.
+pub fn enabled(level: Level) -> bool {
+    VERBOSITY.load(Ordering::Relaxed) >= level as u8
+}
+
+/// Set whether to report nothing but errors.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Whether progress and warnings are left out.
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Report progress on standard output unless run with `--quiet`.
+#[macro_export]
+macro_rules! emit {
+    ($($arg:tt)*) => {
+        if !$crate::log::quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Warn on standard error unless run with `--quiet`.
+#[macro_export]
+macro_rules! eemit {
+    ($($arg:tt)*) => {
+        if !$crate::log::quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Report what is being done when run with `-v`.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Info) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Report the details of a capture when run with `-vv`.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Debug) {
+            eprintln!($($arg)*);
+        }
+    };
+}
@@ -0,0 +1,82 @@
+//! Capture images directly from the X11 server.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ConnectionExt, ImageFormat, ImageOrder};
+use x11rb::rust_connection::RustConnection;
+
+/// Capture an area of the screen to a PNG image.
+pub fn capture_native(
+    filename: &Path,
+    (x, y): (i32, i32),
+    (width, height): (u32, u32),
+) -> io::Result<()> {
+    let (connection, screen) = connect()?;
+    let setup = connection.setup();
+    let root = &setup.roots[screen];
+
+    let image = connection
+        .get_image(
+            ImageFormat::Z_PIXMAP,
+            root.root,
+            x as i16,
+            y as i16,
+            width as u16,
+            height as u16,
+            !0,
+        )
+        .map_err(io::Error::other)?
+        .reply()
+        .map_err(io::Error::other)?;
+
+    let bits_per_pixel = setup
+        .pixmap_formats
+        .iter()
+        .find(|format| format.depth == image.depth)
+        .map(|format| format.bits_per_pixel);
+    if bits_per_pixel != Some(32) {
+        let message = match bits_per_pixel {
+            Some(bits) => format!("Unsupported screen image with {} bits per pixel", bits),
+            None => "Unknown pixel format of screen image".to_owned(),
+        };
+        return Err(io::Error::new(io::ErrorKind::Unsupported, message));
+    }
+
+    // Each pixel is 32 bits of padding and RGB in the server's byte order.
+    let rgb: Vec<u8> = image
+        .data
+        .chunks_exact(4)
+        .flat_map(|pixel| match setup.image_byte_order {
+            ImageOrder::LSB_FIRST => [pixel[2], pixel[1], pixel[0]],
+            _ => [pixel[1], pixel[2], pixel[3]],
+        })
+        .collect();
+
+    let file = File::create(filename)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .map_err(io::Error::other)?
+        .write_image_data(&rgb)
+        .map_err(io::Error::other)
+}
+
+/// Get the size of the screen.
+pub fn native_screen_size() -> io::Result<(u32, u32)> {
+    let (connection, screen) = connect()?;
+    let root = &connection.setup().roots[screen];
+    Ok((root.width_in_pixels as u32, root.height_in_pixels as u32))
+}
+
+/// Connect to the X11 display.
+///
+/// Fails if there is no display to connect to, as in a Wayland or SSH session.
+fn connect() -> io::Result<(RustConnection, usize)> {
+    x11rb::connect(None)
+        .map_err(|e| io::Error::other(format!("Could not connect to the X11 display: {}", e)))
+}
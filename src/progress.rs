@@ -0,0 +1,63 @@
+//! Report recording progress for programs wrapping screencap.
+//!
+//! Each progress update from ffmpeg is written to standard error as a JSON
+//! object on its own line:
+//!
+//! | Key       | Value                                          |
+//! |-----------|------------------------------------------------|
+//! | `elapsed` | Seconds of video recorded so far               |
+//! | `frames`  | Frames encoded so far                          |
+//! | `fps`     | Frames encoded per second                      |
+//! | `drops`   | Frames dropped so far                          |
+//! | `speed`   | Encoding speed relative to real time, or null  |
+//! | `done`    | Whether this is the last update                |
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::ChildStdout;
+use std::str::FromStr;
+use std::thread::{self, JoinHandle};
+
+use serde_json::json;
+
+/// Read progress from ffmpeg's `-progress` output and report each update.
+///
+/// ffmpeg writes updates as blocks of `key=value` lines ending with a
+/// `progress` key.
+pub fn report_progress(progress: ChildStdout) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut values = HashMap::new();
+        for line in BufReader::new(progress).lines().map_while(Result::ok) {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim().to_owned(), value.trim().to_owned()),
+                None => continue,
+            };
+            if key != "progress" {
+                values.insert(key, value);
+                continue;
+            }
+
+            let elapsed = parse::<f64>(&values, "out_time_us").map(|micros| micros / 1_000_000.0);
+            let speed = values
+                .get("speed")
+                .and_then(|speed| speed.strip_suffix('x'))
+                .and_then(|speed| speed.trim().parse::<f64>().ok());
+
+            let event = json!({
+                "elapsed": elapsed,
+                "frames": parse::<u64>(&values, "frame"),
+                "fps": parse::<f64>(&values, "fps"),
+                "drops": parse::<u64>(&values, "drop_frames"),
+                "speed": speed,
+                "done": value == "end",
+            });
+            eprintln!("{}", event);
+            values.clear();
+        }
+    })
+}
+
+/// Parse the value of a progress key.
+fn parse<T: FromStr>(values: &HashMap<String, String>, key: &str) -> Option<T> {
+    values.get(key)?.parse().ok()
+}
@@ -0,0 +1,63 @@
+//! Summarise recordings as contact sheets.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use crate::args::ContactSheet;
+use crate::exec;
+use crate::util::*;
+
+/// Get the duration of a video in seconds.
+fn video_duration(filename: &Path) -> Option<f64> {
+    command_output(exec!(ffprobe
+        -v error
+        -show_entries ("format=duration")
+        -of ("default=noprint_wrappers=1:nokey=1")
+        (filename.display())
+    ))
+    .ok()?
+    .next()?
+    .trim()
+    .parse()
+    .ok()
+}
+
+/// Tile evenly spaced frames from a video into a single image next to it.
+///
+/// Gets the path of the contact sheet.
+pub fn contact_sheet(filename: &Path, sheet: &ContactSheet) -> io::Result<PathBuf> {
+    let output = filename.with_extension("sheet.png");
+    let duration = video_duration(filename).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Could not read the video duration",
+        )
+    })?;
+
+    // Frames are taken at a rate that spreads them over the whole video.
+    let frames = sheet.columns * sheet.rows;
+    let filter = format!(
+        "fps={:.6},scale={}:-2,tile={}x{}",
+        frames as f64 / duration.max(0.001),
+        sheet.width,
+        sheet.columns,
+        sheet.rows
+    );
+
+    let status = exec!(
+        ffmpeg
+            - hide_banner
+            - y
+            - i(filename.display())
+            - vf(filter)("-frames:v")(1)(output.display())
+    )
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .map_err(running("ffmpeg"))?;
+    check_status(status, "make the contact sheet")?;
+
+    Ok(output)
+}
@@ -0,0 +1,80 @@
+//! Warn when encoding can't keep up with recording.
+
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::eemit;
+
+/// How long encoding has to stay slower than real time to be warned about.
+const SUSTAINED: Duration = Duration::from_secs(10);
+
+/// Get the encoding speed from an ffmpeg progress line.
+///
+/// Progress lines include a speed such as `speed=0.85x`.
+fn parse_speed(line: &str) -> Option<f64> {
+    let (_, speed) = line.rsplit_once("speed=")?;
+    speed
+        .split_whitespace()
+        .next()?
+        .strip_suffix('x')?
+        .parse()
+        .ok()
+}
+
+/// Get a framerate that an encoder running at the given speed could keep up
+/// with.
+pub fn suggested_framerate(framerate: u64, speed: f64) -> u64 {
+    ((framerate as f64 * speed).floor() as u64).max(1)
+}
+
+/// Watches ffmpeg's progress for encoding that stays slower than real time.
+pub struct SpeedWatch {
+    thread: JoinHandle<Option<f64>>,
+}
+
+impl SpeedWatch {
+    /// Start watching the lines ffmpeg writes to standard error.
+    ///
+    /// Watching ends when ffmpeg closes standard error as it exits.
+    pub fn start(lines: Receiver<String>, framerate: u64) -> Self {
+        let thread = thread::spawn(move || {
+            let mut slowest = None;
+            let mut slow_since = None;
+            let mut warned = false;
+
+            for speed in lines.iter().filter_map(|line| parse_speed(&line)) {
+                if speed >= 1.0 {
+                    slow_since = None;
+                    continue;
+                }
+
+                let since = *slow_since.get_or_insert_with(Instant::now);
+                if since.elapsed() < SUSTAINED {
+                    continue;
+                }
+
+                slowest = Some(slowest.map_or(speed, |slowest: f64| slowest.min(speed)));
+                if !warned {
+                    warned = true;
+                    eemit!(
+                        "Warning: encoding is running at {}x real time and frames will be \
+                         dropped, try -R {} next time",
+                        speed,
+                        suggested_framerate(framerate, speed)
+                    );
+                }
+            }
+
+            slowest
+        });
+
+        SpeedWatch { thread }
+    }
+
+    /// Wait for ffmpeg to finish and get the slowest sustained encoding speed,
+    /// if encoding was ever slower than real time for long.
+    pub fn stop(self) -> Option<f64> {
+        self.thread.join().expect("Speed watching thread")
+    }
+}
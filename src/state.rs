@@ -0,0 +1,92 @@
+//! Remember the last capture so it can be repeated.
+
+use std::env::var_os;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::ErrorKind;
+use serde_json::{json, Value};
+
+use crate::eemit;
+
+/// The arguments of the last capture along with the geometry it resolved to.
+pub struct LastRun {
+    pub args: Vec<OsString>,
+    pub geometry: Option<(String, String)>,
+}
+
+/// Get the file the last capture is saved in.
+fn last_run_file() -> Option<PathBuf> {
+    let mut path = match var_os("XDG_STATE_HOME") {
+        Some(state) => PathBuf::from(state),
+        None => {
+            let mut home = PathBuf::from(var_os("HOME")?);
+            home.push(".local/state");
+            home
+        }
+    };
+    path.push("screencap/last-run.json");
+    Some(path)
+}
+
+/// Save the arguments of a capture along with the geometry it resolved to.
+///
+/// Failures are reported as warnings as the capture itself succeeded.
+pub fn save_last_run(args: &[OsString], geometry: Option<&(String, String)>) {
+    let path = match last_run_file() {
+        Some(path) => path,
+        None => return eemit!("Warning: no HOME to save the last capture in"),
+    };
+
+    let args: Vec<_> = args.iter().map(|arg| arg.to_string_lossy()).collect();
+    let state = json!({
+        "args": args,
+        "geometry": geometry.map(|(resolution, region)| json!({
+            "resolution": resolution,
+            "region": region,
+        })),
+    });
+
+    let saved = fs::create_dir_all(path.parent().expect("State directory"))
+        .and_then(|_| fs::write(&path, state.to_string()));
+    if let Err(e) = saved {
+        eemit!(
+            "Warning: could not save the last capture to {:?}: {}",
+            path,
+            e
+        );
+    }
+}
+
+/// Load the arguments and geometry of the last capture.
+pub fn load_last_run() -> Result<LastRun, clap::Error> {
+    let error = |message: String| clap::Error::with_description(&message, ErrorKind::Io);
+
+    let path =
+        last_run_file().ok_or_else(|| error("No HOME to load the last capture from".into()))?;
+    let contents = fs::read_to_string(&path).map_err(|e| {
+        error(format!(
+            "No previous capture to repeat in {:?}: {}",
+            path, e
+        ))
+    })?;
+    let state: Value = serde_json::from_str(&contents)
+        .map_err(|e| error(format!("Could not read last capture {:?}: {}", path, e)))?;
+    let invalid = || error(format!("Could not read last capture {:?}", path));
+
+    let args = state["args"]
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|arg| arg.as_str().map(OsString::from).ok_or_else(invalid))
+        .collect::<Result<_, _>>()?;
+
+    let geometry = &state["geometry"];
+    let geometry = match (geometry["resolution"].as_str(), geometry["region"].as_str()) {
+        (Some(resolution), Some(region)) => Some((resolution.to_owned(), region.to_owned())),
+        _ => None,
+    };
+
+    Ok(LastRun { args, geometry })
+}
@@ -0,0 +1,67 @@
+//! Stop ffmpeg cleanly.
+
+use std::io::Write;
+use std::process::ChildStdin;
+use std::sync::{Arc, Mutex};
+
+/// Stops ffmpeg by asking it to quit, letting it finish writing the capture.
+///
+/// Clones share the same ffmpeg so that anything can stop it.
+#[derive(Clone, Default)]
+pub struct Stopper(Arc<Mutex<Stop>>);
+
+/// The ffmpeg to stop, and whether it has been asked to stop.
+#[derive(Default)]
+struct Stop {
+    ffmpeg: Option<ChildStdin>,
+    stopped: bool,
+}
+
+impl Stopper {
+    /// Stop ffmpeg through its standard input.
+    pub fn new(ffmpeg: ChildStdin) -> Self {
+        let stopper = Stopper::default();
+        stopper.attach(ffmpeg);
+        stopper
+    }
+
+    /// Stop this ffmpeg from now on, in place of any before it.
+    ///
+    /// If asked to stop before ffmpeg was started, it is stopped at once.
+    pub fn attach(&self, ffmpeg: ChildStdin) {
+        let mut stop = self.0.lock().expect("Lock ffmpeg standard input");
+        stop.ffmpeg = Some(ffmpeg);
+        if stop.stopped {
+            quit(&mut stop);
+        }
+    }
+
+    /// Ask ffmpeg to stop, returning whether it had not already been asked.
+    pub fn stop(&self) -> bool {
+        let mut stop = self.0.lock().expect("Lock ffmpeg standard input");
+        if stop.stopped {
+            return false;
+        }
+        stop.stopped = true;
+        quit(&mut stop);
+        true
+    }
+
+    /// Ask the ffmpeg running now to stop without stopping what it records,
+    /// so that another can be attached to carry on.
+    pub fn pause(&self) {
+        quit(&mut self.0.lock().expect("Lock ffmpeg standard input"));
+    }
+
+    /// Whether ffmpeg has been asked to stop.
+    pub fn stopped(&self) -> bool {
+        self.0.lock().expect("Lock ffmpeg standard input").stopped
+    }
+}
+
+/// Send ffmpeg a `q` to quit, if it has been started.
+fn quit(stop: &mut Stop) {
+    if let Some(mut ffmpeg) = stop.ffmpeg.take() {
+        let _ = ffmpeg.write_all(b"q\n");
+    }
+}
@@ -0,0 +1,195 @@
+//! Expand the templates used to name captures.
+
+use std::env::var;
+use std::fmt::Write;
+
+use chrono::prelude::*;
+use hostname::get_hostname;
+
+use crate::exec;
+use crate::util::*;
+
+/// The default template, naming captures by host and time.
+pub const DEFAULT_TEMPLATE: &str = "{host}.{date}";
+
+/// The default format of `{date}`, which sorts by time.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d.%H%M.%S";
+
+/// Placeholders that can be used in templates.
+const PLACEHOLDERS: &[&str] = &["host", "date", "clipboard", "selection", "git-branch"];
+
+/// The longest value substituted for a dynamic placeholder.
+const MAX_VALUE_LENGTH: usize = 64;
+
+/// Check that a template only uses known placeholders.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("{:?} has an unclosed placeholder", template))?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "{{{}}} is not one of {}",
+                name,
+                PLACEHOLDERS
+                    .iter()
+                    .map(|name| format!("{{{}}}", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+/// Check that a chrono format for `{date}` can be used in file names.
+pub fn validate_date_format(format: &str) -> Result<(), String> {
+    let mut date = String::new();
+    write!(date, "{}", Local::now().format(format))
+        .map_err(|_| format!("{:?} is not a valid date format", format))?;
+    if date.contains('/') {
+        return Err(format!("{:?} gives dates containing '/'", format));
+    }
+    Ok(())
+}
+
+/// Replace each placeholder in a template with its value, formatting
+/// `{date}` with the given chrono format.
+pub fn expand_template(template: &str, date_format: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&placeholder_value(&rest[start + 1..end], date_format));
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Get the value of a placeholder.
+///
+/// Dynamic values that cannot be found are empty.
+fn placeholder_value(name: &str, date_format: &str) -> String {
+    match name {
+        "host" => {
+            // A misconfigured host shouldn't stop captures being named.
+            let hostname = get_hostname()
+                .or_else(|| var("HOSTNAME").ok())
+                .filter(|hostname| !hostname.is_empty())
+                .unwrap_or_else(|| "localhost".to_owned());
+            hostname.split('.').next().unwrap().to_owned()
+        }
+        "date" => Local::now().format(date_format).to_string(),
+        "clipboard" => sanitize(&x11_selection("clipboard")),
+        "selection" => sanitize(&x11_selection("primary")),
+        "git-branch" => sanitize(&git_branch()),
+        _ => String::new(),
+    }
+}
+
+/// Get the first line of text in an X11 selection.
+fn x11_selection(selection: &str) -> String {
+    if which("xclip").is_none() {
+        return String::new();
+    }
+    command_output(exec!(xclip - o - selection(selection)))
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .unwrap_or_default()
+}
+
+/// Get the branch of the git repository in the current directory.
+fn git_branch() -> String {
+    if which("git").is_none() {
+        return String::new();
+    }
+    command_output(exec!(git ("rev-parse") ("--abbrev-ref") HEAD))
+        .ok()
+        .and_then(|mut lines| lines.next())
+        .unwrap_or_default()
+}
+
+/// Make a value safe to use in a file name.
+///
+/// Anything other than letters, digits, `-`, `_` and `.` is replaced with `-`.
+fn sanitize(value: &str) -> String {
+    let mut sanitized = String::new();
+    for c in value.trim().chars() {
+        let c = if c.is_alphanumeric() || c == '_' || c == '.' {
+            c
+        } else {
+            '-'
+        };
+        if !(c == '-' && sanitized.ends_with('-')) {
+            sanitized.push(c);
+        }
+    }
+
+    let sanitized = sanitized.trim_matches(|c| c == '-' || c == '.');
+    sanitized.chars().take(MAX_VALUE_LENGTH).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_with_known_placeholders() {
+        assert_eq!(validate_template(DEFAULT_TEMPLATE), Ok(()));
+        assert_eq!(validate_template("{git-branch}-{clipboard}"), Ok(()));
+        assert_eq!(validate_template("no placeholders"), Ok(()));
+    }
+
+    #[test]
+    fn unclosed_placeholder() {
+        assert_eq!(
+            validate_template("{host}.{date"),
+            Err("\"{host}.{date\" has an unclosed placeholder".to_owned())
+        );
+    }
+
+    #[test]
+    fn unknown_placeholder() {
+        let error = validate_template("{host}.{user}").unwrap_err();
+        assert!(error.starts_with("{user} is not one of {host}, {date}"));
+    }
+
+    #[test]
+    fn expand_date_with_format() {
+        assert_eq!(expand_template("shot-{date}.x", "fixed"), "shot-fixed.x");
+        assert_eq!(expand_template("shot-{date", "fixed"), "shot-{date");
+    }
+
+    #[test]
+    fn sanitize_path_separators() {
+        assert_eq!(sanitize("feature/name"), "feature-name");
+        assert_eq!(sanitize("../../etc/passwd"), "etc-passwd");
+        assert_eq!(sanitize(".."), "");
+        assert_eq!(sanitize("a/../b"), "a-..-b");
+        assert!(!sanitize("/tmp/x").contains('/'));
+    }
+
+    #[test]
+    fn sanitize_collapses_and_trims() {
+        assert_eq!(sanitize("  Hello,   World!  "), "Hello-World");
+        assert_eq!(sanitize("v1.2_rc"), "v1.2_rc");
+    }
+
+    #[test]
+    fn sanitize_truncates_long_values() {
+        let long = "x".repeat(MAX_VALUE_LENGTH * 2);
+        assert_eq!(sanitize(&long).len(), MAX_VALUE_LENGTH);
+        assert_eq!(
+            sanitize(&format!("ab/{}", long)),
+            format!("ab-{}", "x".repeat(61))
+        );
+    }
+}
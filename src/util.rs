@@ -1,24 +1,41 @@
 //! Utilities.
 
-use std::env::var;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
-use std::process::{Command, Stdio};
+use std::collections::HashMap;
+use std::env::{var, var_os};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Cursor};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::UNIX_EPOCH;
 
+use serde_json::{json, Value};
+
+use crate::eemit;
+
+/// Build a command from a binary name and its arguments.
+///
+/// Binaries that aren't found are still run by name so that they fail when
+/// the command is run, where callers can report it.
+///
+/// Binaries are found with the `which` in scope where the macro is used, so
+/// tests can replace it with a stub.
 #[macro_export]
 macro_rules! exec {
     ($command:ident $($args:tt)*) => {{
         let mut command: std::process::Command = which(stringify!($command))
-            .expect(&format!("No command {:?} found", stringify!($command)));
+            .unwrap_or_else(|| std::process::Command::new(stringify!($command)));
         exec!(@(&mut command) $($args)*);
         command
     }};
     (($command:expr) $($args:tt)*) => {{
         let command_name = $command.to_string();
         let mut command: std::process::Command = which(&command_name)
-            .expect(&format!("No command {:?} found", command_name));
-        exec!(@(command) $($args)*);
+            .unwrap_or_else(|| std::process::Command::new(&command_name));
+        exec!(@(&mut command) $($args)*);
         command
     }};
     (@($command:expr) --$argument:ident $($args:tt)*) => {
@@ -34,61 +51,119 @@ macro_rules! exec {
         exec!(@($command.arg(&$argument.to_string())) $($args)*)
     };
     (@($command:expr)) => {
-        $command
+        let _ = $command;
     };
 }
 
+/// The file ffmpeg's listings are cached in.
+const CAPABILITIES_FILE: &str = "capabilities.json";
+
+/// The ffmpeg binary to use instead of the one on the PATH.
+static FFMPEG_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Use the given ffmpeg binary for every command run as `ffmpeg`, or the one
+/// on the PATH if none is given.
+///
+/// Each capture sets this, so captures run one after another can each use a
+/// different ffmpeg.
+pub fn use_ffmpeg(path: Option<PathBuf>) {
+    *FFMPEG_PATH.write().expect("Set the ffmpeg path") = path;
+}
+
 /// Create a command from a given binary name.
 pub fn which<P: AsRef<Path>>(binary: P) -> Option<Command> {
-    if binary.as_ref().starts_with("./") && binary.as_ref().exists() {
+    which_in(binary, &var("PATH").ok()?)
+}
+
+/// Create a command from a given binary name, searching a colon-separated
+/// list of directories.
+pub fn which_in<P: AsRef<Path>>(binary: P, search_path: &str) -> Option<Command> {
+    let ffmpeg = FFMPEG_PATH
+        .read()
+        .expect("Read the ffmpeg path")
+        .clone()
+        .filter(|_| binary.as_ref() == Path::new("ffmpeg"));
+    if let Some(ffmpeg) = ffmpeg {
+        Some(Command::new(ffmpeg))
+    } else if binary.as_ref().starts_with("./") && binary.as_ref().exists() {
         Some(Command::new(binary.as_ref()))
     } else {
-        var("PATH")
-            .ok()?
+        search_path
             .split(':')
             .map(|prefix| Path::new(prefix).to_owned())
             .map(|mut prefix| {
                 prefix.push(&binary);
                 prefix
             })
-            .filter(|path| path.exists())
-            .nth(0)
+            .find(|path| is_executable(path))
             .map(Command::new)
     }
 }
 
+/// Whether a path is a regular file that can be executed.
+pub fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
 /// An iterator over the lines output from a command.
-pub fn command_output(mut command: Command) -> impl Iterator<Item = String> {
-    let command_text = format!("{:?}", command);
-    let child = command
-        .stdout(Stdio::piped())
+///
+/// Fails if the command can't be run, such as when it isn't installed.
+pub fn command_output(mut command: Command) -> io::Result<impl Iterator<Item = String>> {
+    let output = command
+        .stdin(Stdio::null())
         .stderr(Stdio::null())
-        .spawn()
-        .expect(&format!("Execute {}", command_text));
+        .output()
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not run {:?}: {}", command, e)))?;
 
-    BufReader::new(child.stdout.expect(&format!("Read from {}", command_text)))
+    Ok(BufReader::new(Cursor::new(output.stdout))
         .lines()
-        .filter(Result::is_ok)
-        .map(Result::unwrap)
+        .map_while(Result::ok))
+}
+
+/// Fail with what was being done if a command didn't finish successfully.
+pub fn check_status(status: ExitStatus, doing: &str) -> io::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("Could not {}: {}", doing, status)))
+    }
+}
+
+/// Add the program that couldn't be started to an error.
+pub fn running(program: &str) -> impl Fn(io::Error) -> io::Error + '_ {
+    move |e| io::Error::new(e.kind(), format!("Could not run {}: {}", program, e))
 }
 
 /// Get the nth word in a line as a string.
-pub fn line_nth(line: String, nth: usize) -> String {
-    line.trim()
-        .split_whitespace()
-        .nth(nth)
-        .expect(&format!("Read item #{} from {:?}", nth, line))
-        .to_owned()
+///
+/// Fails if the line is shorter, as when a command's output isn't as expected.
+pub fn line_nth(line: String, nth: usize) -> io::Result<String> {
+    match line.split_whitespace().nth(nth) {
+        Some(item) => Ok(item.to_owned()),
+        None => {
+            let message = format!("Could not read item #{} from {:?}", nth, line);
+            Err(io::Error::new(io::ErrorKind::InvalidData, message))
+        }
+    }
 }
 
 /// Get the next line matching the given predicate.
+///
+/// Fails if no line matches, as when a command's output isn't as expected.
 pub fn get_line(
     lines: impl Iterator<Item = String>,
     mut predicate: impl FnMut(&str) -> bool,
-) -> (impl Iterator<Item = String>, String) {
+) -> io::Result<(impl Iterator<Item = String>, String)> {
     let mut lines = lines.skip_while(move |s| !predicate(s));
-    let line = lines.next().expect("Read line matching predicate");
-    (lines, line)
+    match lines.next() {
+        Some(line) => Ok((lines, line)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Command output is missing an expected line",
+        )),
+    }
 }
 
 /// Get the neth item in the line matching the predicate.
@@ -96,9 +171,129 @@ pub fn get_nth_from_line(
     lines: impl Iterator<Item = String>,
     predicate: impl FnMut(&str) -> bool,
     nth: usize,
-) -> (impl Iterator<Item = String>, String) {
-    let (lines, line) = get_line(lines, predicate);
-    (lines, line_nth(line, nth))
+) -> io::Result<(impl Iterator<Item = String>, String)> {
+    let (lines, line) = get_line(lines, predicate)?;
+    Ok((lines, line_nth(line, nth)?))
+}
+
+/// Whether to probe ffmpeg again rather than use the cached listings.
+static REFRESH_CAPABILITIES: AtomicBool = AtomicBool::new(false);
+
+/// Probe what ffmpeg supports again, replacing the cached listings.
+pub fn refresh_capabilities() {
+    REFRESH_CAPABILITIES.store(true, Ordering::Relaxed);
+}
+
+/// Get a file in screencap's cache directory.
+pub fn cache_file(name: &str) -> Option<PathBuf> {
+    let mut path = match var_os("XDG_CACHE_HOME") {
+        Some(cache) => PathBuf::from(cache),
+        None => {
+            let mut home = PathBuf::from(var_os("HOME")?);
+            home.push(".cache");
+            home
+        }
+    };
+    path.push("screencap");
+    path.push(name);
+    Some(path)
+}
+
+/// Identify the ffmpeg binary by its path and when it was last changed, so
+/// that cached listings are dropped once it is replaced.
+fn ffmpeg_version_key() -> Option<String> {
+    let ffmpeg = PathBuf::from(which("ffmpeg")?.get_program());
+    let modified = fs::metadata(&ffmpeg)
+        .and_then(|metadata| metadata.modified())
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?;
+    Some(format!("{}@{}", ffmpeg.display(), modified.as_nanos()))
+}
+
+/// The lines ffmpeg lists for each flag.
+type Listings = HashMap<String, Vec<String>>;
+
+/// Load the cached listings if they came from the current ffmpeg.
+fn load_capabilities(key: &str) -> Listings {
+    if REFRESH_CAPABILITIES.load(Ordering::Relaxed) {
+        return HashMap::new();
+    }
+
+    let cached: Option<Value> = cache_file(CAPABILITIES_FILE)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok());
+    let cached = match cached {
+        Some(cached) if cached["ffmpeg"].as_str() == Some(key) => cached,
+        _ => return HashMap::new(),
+    };
+
+    cached["listings"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(flag, lines)| {
+            let lines = lines
+                .as_array()?
+                .iter()
+                .map(|line| line.as_str().map(str::to_owned));
+            Some((flag.clone(), lines.collect::<Option<_>>()?))
+        })
+        .collect()
+}
+
+/// Save the listings for the current ffmpeg.
+///
+/// Failures are reported as warnings as ffmpeg is just probed again next time.
+fn save_capabilities(key: &str, listings: &Listings) {
+    let path = match cache_file(CAPABILITIES_FILE) {
+        Some(path) => path,
+        None => return,
+    };
+    let cache = json!({
+        "ffmpeg": key,
+        "listings": listings,
+    });
+
+    let saved = fs::create_dir_all(path.parent().expect("Cache directory"))
+        .and_then(|_| fs::write(&path, cache.to_string()));
+    if let Err(e) = saved {
+        eemit!(
+            "Warning: could not cache ffmpeg capabilities to {:?}: {}",
+            path,
+            e
+        );
+    }
+}
+
+/// Get the lines ffmpeg lists for a flag such as `-formats`.
+///
+/// Listings are kept for the rest of the run and cached between runs until
+/// ffmpeg changes.
+///
+/// Fails if ffmpeg can't be run.
+fn listing(flag: &str) -> io::Result<Vec<String>> {
+    static LISTINGS: Mutex<Option<(Option<String>, Listings)>> = Mutex::new(None);
+    let key = ffmpeg_version_key();
+    let mut loaded = LISTINGS.lock().expect("Lock ffmpeg listings");
+
+    // Listings from another ffmpeg, chosen by an earlier capture, are dropped.
+    if loaded.as_ref().is_none_or(|(loaded, _)| *loaded != key) {
+        let listings = key.as_deref().map(load_capabilities).unwrap_or_default();
+        *loaded = Some((key.clone(), listings));
+    }
+    let (_, listings) = loaded.get_or_insert_with(Default::default);
+
+    if let Some(lines) = listings.get(flag) {
+        return Ok(lines.clone());
+    }
+
+    let lines: Vec<String> = command_output(exec!(ffmpeg(flag)))?.collect();
+    listings.insert(flag.to_owned(), lines.clone());
+    if let Some(key) = &key {
+        save_capabilities(key, listings);
+    }
+    Ok(lines)
 }
 
 #[derive(Debug, Clone)]
@@ -114,49 +309,73 @@ enum Type {
     Audio,
     Video,
     Subtitle,
+    Data,
+    Attachment,
     Format,
 }
 use self::Type::*;
 
+/// Characters used in the flag columns of ffmpeg's listings.
+const FLAG_CHARS: &str = "DEVASTFXBILd.";
+
 impl FromStr for Type {
     type Err = ();
 
+    /// Get the type from the flags of a listing, with the columns of flags
+    /// joined together.
+    ///
+    /// Formats are flagged as demuxing (`D`), muxing (`E`) and being a device
+    /// (`d`), the last only since ffmpeg 6. Encoders and decoders are listed
+    /// with the type first, as in `V....D`, while codecs are listed with the
+    /// type after whether they decode and encode, as in `DEV.LS`. The type is
+    /// one of `V`, `A`, `S`, `D` for data or `T` for attachments, so `D` is
+    /// only a type where the type column is.
     fn from_str(s: &str) -> Result<Type, ()> {
-        match s {
-            "D" | "E" | "DE" => return Ok(Format),
-            _ => {}
-        };
-
-        if s.len() != 6 {
-            return Err(());
+        if !s.is_empty() && s.chars().all(|c| "DEd".contains(c)) {
+            return Ok(Format);
         }
 
-        match (&s[0..1], &s[2..3]) {
-            ("A", _) | (_, "A") => Ok(Audio),
-            ("V", _) | (_, "V") => Ok(Video),
-            ("S", _) | (_, "S") => Ok(Subtitle),
+        let flags: Vec<char> = s.chars().collect();
+        let type_ = match flags.as_slice() {
+            ['D' | '.', 'E' | '.', type_, ..] if "VASDT".contains(*type_) => *type_,
+            [type_, ..] => *type_,
+            [] => return Err(()),
+        };
+        match type_ {
+            'V' => Ok(Video),
+            'A' => Ok(Audio),
+            'S' => Ok(Subtitle),
+            'D' => Ok(Data),
+            'T' => Ok(Attachment),
             _ => Err(()),
         }
     }
 }
 
 impl FFMPEGSupport {
-    pub fn formats() -> impl Iterator<Item = FFMPEGSupport> {
-        Self::parse(exec!(ffmpeg - formats))
+    pub fn formats() -> io::Result<impl Iterator<Item = FFMPEGSupport>> {
+        Ok(Self::parse(listing("-formats")?)
             .filter(|(_, t)| *t == Format)
-            .map(|(s, _)| s)
+            .map(|(s, _)| s))
     }
 
-    pub fn video_encoders() -> impl Iterator<Item = FFMPEGSupport> {
-        Self::encoders()
+    pub fn video_encoders() -> io::Result<impl Iterator<Item = FFMPEGSupport>> {
+        Ok(Self::encoders()?
             .filter(|(_, t)| *t == Video)
-            .map(|(s, _)| s)
+            .map(|(s, _)| s))
     }
 
-    pub fn audio_encoders() -> impl Iterator<Item = FFMPEGSupport> {
-        Self::encoders()
+    pub fn audio_encoders() -> io::Result<impl Iterator<Item = FFMPEGSupport>> {
+        Ok(Self::encoders()?
             .filter(|(_, t)| *t == Audio)
-            .map(|(s, _)| s)
+            .map(|(s, _)| s))
+    }
+
+    /// Find a format or encoder by name.
+    pub fn find(name: &str) -> io::Result<Option<FFMPEGSupport>> {
+        Ok(Self::formats()?
+            .chain(Self::encoders()?.map(|(s, _)| s))
+            .find(|s| s.has_name(name)))
     }
 
     pub fn has_name(&self, name: &str) -> bool {
@@ -173,6 +392,11 @@ impl FFMPEGSupport {
         &self.names[0]
     }
 
+    /// The description ffmpeg gives, such as `libx264 H.264 / AVC / MPEG-4 AVC`.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
     pub fn encode(&self) -> bool {
         self.encode
     }
@@ -181,50 +405,41 @@ impl FFMPEGSupport {
         self.decode
     }
 
-    fn encoders() -> impl Iterator<Item = (FFMPEGSupport, Type)> {
-        Self::parse(exec!(ffmpeg - encoders)).map(|(mut s, t)| {
+    fn encoders() -> io::Result<impl Iterator<Item = (FFMPEGSupport, Type)>> {
+        Ok(Self::parse(listing("-encoders")?).map(|(mut s, t)| {
             s.encode = true;
             s.decode = false;
             (s, t)
-        })
+        }))
     }
 
-    fn parse(mut command: Command) -> impl Iterator<Item = (FFMPEGSupport, Type)> {
-        let child = command
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .spawn()
-            .expect("Launching ffmpeg process");
-
-        let output = child.stdout.expect("Reading child output");
-
-        BufReader::new(output)
-            .lines()
-            .filter(Result::is_ok)
-            .map(Result::unwrap)
-            .map(Self::decode_line)
-            .filter(Option::is_some)
-            .map(Option::unwrap)
+    fn parse(lines: Vec<String>) -> impl Iterator<Item = (FFMPEGSupport, Type)> {
+        lines.into_iter().filter_map(Self::decode_line)
     }
 
+    /// Read a line of an ffmpeg listing.
+    ///
+    /// The width of the flag columns and the spacing around them differ
+    /// between ffmpeg versions, so flags are taken to be every word before the
+    /// names that is made up only of flag characters.
     fn decode_line(line: String) -> Option<(FFMPEGSupport, Type)> {
-        let line = line.trim();
-
-        let code_end = line.find(char::is_whitespace)?;
-        let code = line[..code_end].trim();
+        let mut words = line.split_whitespace().peekable();
+        let mut code = String::new();
+        while let Some(flags) = words.next_if(|word| word.chars().all(|c| FLAG_CHARS.contains(c))) {
+            code.push_str(flags);
+        }
 
         let type_ = code.parse().ok()?;
 
-        let line = line[code_end..].trim();
-        let names_end = line.find(char::is_whitespace)?;
-        let names = line[..names_end].trim();
+        // Headers explain each flag as `V..... = Video`.
+        let names = words.next().filter(|names| *names != "=")?;
         let names = names.split(',').map(|s| s.to_owned()).collect();
 
-        let description = line[names_end..].trim().to_owned();
+        let description = words.collect::<Vec<_>>().join(" ");
 
         let (decode, encode) = match type_ {
-            Format => (code.contains("D"), code.contains("E")),
-            _ => (&code[0..1] == "D", &code[1..2] == "E"),
+            Format => (code.contains('D'), code.contains('E')),
+            _ => (code.starts_with('D'), code[1..].starts_with('E')),
         };
 
         let support = FFMPEGSupport {
@@ -237,3 +452,455 @@ impl FFMPEGSupport {
         Some((support, type_))
     }
 }
+
+/// Find the first supported codec from a list of names in order of
+/// preference.
+pub fn find_codec(
+    codecs: impl Iterator<Item = FFMPEGSupport>,
+    names: &[&str],
+    filter: impl Fn(&FFMPEGSupport) -> bool,
+) -> Option<String> {
+    find_codecs(codecs, names, filter).into_iter().next()
+}
+
+/// Find every supported codec from a list of names in order of preference.
+pub fn find_codecs(
+    codecs: impl Iterator<Item = FFMPEGSupport>,
+    names: &[&str],
+    filter: impl Fn(&FFMPEGSupport) -> bool,
+) -> Vec<String> {
+    let mut found = HashMap::new();
+
+    for codec in codecs {
+        for name in names {
+            if codec.has_name(name) && filter(&codec) {
+                found.insert(name, codec.clone());
+            }
+        }
+    }
+
+    let mut codecs: Vec<String> = Vec::new();
+    for name in names {
+        if let Some(codec) = found.remove(name) {
+            if !codecs.iter().any(|found| found == codec.name()) {
+                codecs.push(codec.name().to_owned());
+            }
+        }
+    }
+
+    codecs
+}
+
+/// The argument telling ffmpeg whether to replace an output that exists.
+pub fn overwrite_arg(overwrite: bool) -> &'static str {
+    if overwrite {
+        "-y"
+    } else {
+        "-n"
+    }
+}
+
+/// Number a path by adding to its file name before the extension, such that
+/// `shot.png` becomes `shot-2.png`.
+pub fn numbered_path(path: &Path, number: u64) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, number, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, number),
+    };
+    path.with_file_name(name)
+}
+
+/// The prefix of files written next to where a capture is saved while it is
+/// being captured.
+const PARTIAL_PREFIX: &str = ".partial.";
+
+/// Get the file name a capture is saved as.
+///
+/// Fails for paths that don't name a file, such as `/` or `shots/..`.
+pub fn capture_file_name(path: &Path) -> io::Result<String> {
+    match path.file_name() {
+        Some(name) => Ok(name.to_string_lossy().into_owned()),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} has no file name to save the capture as", path),
+        )),
+    }
+}
+
+/// Get the path a capture is written to before it is saved to a path.
+///
+/// The extension is kept so that tools still write the right format.
+pub fn partial_path(path: &Path) -> io::Result<PathBuf> {
+    let name = capture_file_name(path)?;
+    Ok(path.with_file_name(format!("{}{}", PARTIAL_PREFIX, name)))
+}
+
+/// Get where a capture written to a partial path is saved, or none if the
+/// path isn't partial.
+pub fn saved_path(partial: &Path) -> Option<PathBuf> {
+    let name = partial.file_name()?.to_string_lossy();
+    let name = name.strip_prefix(PARTIAL_PREFIX)?;
+    Some(partial.with_file_name(name))
+}
+
+/// Move a complete capture from its partial path to where it is saved,
+/// getting the path it was saved to.
+///
+/// Paths that aren't partial are already where they are saved.
+pub fn save_partial(partial: &Path) -> io::Result<PathBuf> {
+    let path = match saved_path(partial) {
+        Some(path) => path,
+        None => return Ok(partial.to_owned()),
+    };
+    fs::rename(partial, &path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not move {:?} to {:?}: {}", partial, path, e),
+        )
+    })?;
+    Ok(path)
+}
+
+/// A capture being written to its partial path, which is removed unless the
+/// capture is saved.
+///
+/// Captures that fail or panic part way through are then never left where
+/// the user would find them.
+pub struct PartialFile {
+    path: PathBuf,
+    saved: bool,
+}
+
+impl PartialFile {
+    /// Start a capture to be saved to a path, removing anything left at the
+    /// partial path by a capture that was killed.
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let path = partial_path(path)?;
+        let _ = fs::remove_file(&path);
+        Ok(PartialFile { path, saved: false })
+    }
+
+    /// The path the capture is written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the complete capture to where it is saved.
+    pub fn save(mut self) -> io::Result<PathBuf> {
+        self.saved = true;
+        save_partial(&self.path)
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if !self.saved {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Arguments to move the index of an MP4 or MOV file to the start so that it
+/// can be played while it downloads.
+///
+/// These formats otherwise write the index at the end, while matroska doesn't
+/// need moving.
+pub fn faststart_args(format: &str) -> &'static [&'static str] {
+    match format {
+        "mp4" | "mov" => &["-movflags", "+faststart"],
+        _ => &[],
+    }
+}
+
+/// Quote an argument so a shell passes it through unchanged.
+pub fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Put an argument into a shell command in place of each `{}`, or at the end
+/// if there are none.
+pub fn shell_substitute(command: &str, arg: &str) -> String {
+    let arg = shell_quote(arg);
+    if command.contains("{}") {
+        command.replace("{}", &arg)
+    } else {
+        format!("{} {}", command, arg)
+    }
+}
+
+/// Format a command so that it can be pasted into a shell.
+pub fn shell_command(command: &Command) -> String {
+    Some(command.get_program())
+        .into_iter()
+        .chain(command.get_args())
+        .map(|arg| shell_quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::*;
+
+    fn decode(line: &str) -> (FFMPEGSupport, Type) {
+        FFMPEGSupport::decode_line(line.to_owned()).expect("Decode line")
+    }
+
+    #[test]
+    fn decode_ffmpeg_4_lines() {
+        let (format, type_) = decode(" DE matroska,webm   Matroska / WebM");
+        assert_eq!(type_, Format);
+        assert!(format.has_name("webm") && format.decode && format.encode);
+
+        let (format, type_) = decode(" D  x11grab         X11 screen capture, using XCB");
+        assert_eq!(type_, Format);
+        assert_eq!(format.name(), "x11grab");
+        assert!(format.decode && !format.encode);
+
+        let (encoder, type_) = decode(
+            " V..... libx264              libx264 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (codec h264)",
+        );
+        assert_eq!(type_, Video);
+        assert_eq!(encoder.name(), "libx264");
+    }
+
+    #[test]
+    fn decode_ffmpeg_5_lines() {
+        let (encoder, type_) = decode(
+            " V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (codec h264)",
+        );
+        assert_eq!(type_, Video);
+        assert_eq!(encoder.name(), "libx264");
+
+        let (encoder, type_) = decode(" A....D aac                  AAC (Advanced Audio Coding)");
+        assert_eq!(type_, Audio);
+        assert_eq!(encoder.name(), "aac");
+        assert_eq!(encoder.description, "AAC (Advanced Audio Coding)");
+    }
+
+    #[test]
+    fn decode_ffmpeg_6_lines() {
+        let (format, type_) = decode(" D d x11grab         X11 screen capture, using XCB");
+        assert_eq!(type_, Format);
+        assert_eq!(format.name(), "x11grab");
+        assert!(format.decode && !format.encode);
+
+        let (format, type_) = decode(" DEd pulse           Pulse audio output");
+        assert_eq!(type_, Format);
+        assert!(format.has_name("pulse") && format.decode && format.encode);
+
+        let (encoder, type_) =
+            decode("V....D h264_nvenc           NVIDIA NVENC H.264 encoder (codec h264)");
+        assert_eq!(type_, Video);
+        assert_eq!(encoder.name(), "h264_nvenc");
+        assert_eq!(
+            encoder.description(),
+            "NVIDIA NVENC H.264 encoder (codec h264)"
+        );
+    }
+
+    #[test]
+    fn decode_data_and_attachment_lines() {
+        let (codec, type_) = decode(" D.D... bin_data             binary data");
+        assert_eq!(type_, Data);
+        assert_eq!(codec.name(), "bin_data");
+        assert!(codec.decode && !codec.encode);
+
+        let (codec, type_) = decode(" DED... bin_data             binary data");
+        assert_eq!(type_, Data);
+        assert!(codec.decode && codec.encode);
+
+        let (codec, type_) = decode(" ..T... ttf                  TrueType font");
+        assert_eq!(type_, Attachment);
+        assert!(!codec.decode && !codec.encode);
+
+        let (_, type_) = decode(" DEV.LS h264                 H.264 / AVC / MPEG-4 AVC");
+        assert_eq!(type_, Video);
+        let (_, type_) = decode(" DES... ass                  ASS (Advanced SSA) subtitle");
+        assert_eq!(type_, Subtitle);
+
+        let (encoder, type_) = decode(" D..... bin_data             binary data");
+        assert_eq!(type_, Data);
+        assert_eq!(encoder.name(), "bin_data");
+    }
+
+    fn listing(lines: &[&str]) -> Vec<FFMPEGSupport> {
+        lines.iter().map(|line| decode(line).0).collect()
+    }
+
+    #[test]
+    fn find_codec_follows_preference_order() {
+        let codecs = listing(&[
+            " V....D libx264              libx264 H.264",
+            " V....D h264_nvenc           NVIDIA NVENC H.264 encoder",
+            " V....D h264_qsv             H.264 (Intel Quick Sync Video acceleration)",
+        ]);
+        let names = ["h264_nvenc", "h264_qsv", "libx264", "h264"];
+
+        let found = find_codec(codecs.clone().into_iter(), &names, |_| true);
+        assert_eq!(found.as_deref(), Some("h264_nvenc"));
+
+        let found = find_codecs(codecs.into_iter(), &names, |_| true);
+        assert_eq!(found, ["h264_nvenc", "h264_qsv", "libx264"]);
+    }
+
+    #[test]
+    fn find_codec_honours_filter() {
+        let formats = listing(&[
+            " D  x11grab         X11 screen capture, using XCB",
+            " DE matroska,webm   Matroska / WebM",
+            "  E mp4             MP4 (MPEG-4 Part 14)",
+        ]);
+
+        let found = find_codec(
+            formats.clone().into_iter(),
+            &["x11grab", "matroska"],
+            FFMPEGSupport::encode,
+        );
+        assert_eq!(found.as_deref(), Some("matroska"));
+
+        let found = find_codec(formats.into_iter(), &["mp4"], FFMPEGSupport::decode);
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn find_codec_matches_alias_names() {
+        let formats = listing(&[" DE matroska,webm   Matroska / WebM"]);
+        let found = find_codec(formats.into_iter(), &["webm"], |_| true);
+        assert_eq!(found.as_deref(), Some("matroska"));
+    }
+
+    #[test]
+    fn skip_listing_headers() {
+        assert!(FFMPEGSupport::decode_line(" D. = Demuxing supported".to_owned()).is_none());
+        assert!(FFMPEGSupport::decode_line(" V..... = Video".to_owned()).is_none());
+        assert!(FFMPEGSupport::decode_line(" ------".to_owned()).is_none());
+        assert!(FFMPEGSupport::decode_line(String::new()).is_none());
+    }
+
+    /// Stands in for `which` in `exec!` so that no binaries are looked up.
+    fn which<P: AsRef<Path>>(binary: P) -> Option<Command> {
+        Some(Command::new(Path::new("/stub").join(binary)))
+    }
+
+    fn program_and_args(command: &Command) -> (&OsStr, Vec<&OsStr>) {
+        (command.get_program(), command.get_args().collect())
+    }
+
+    #[test]
+    fn exec_expands_arguments() {
+        let command = exec!(ffmpeg -hide_banner --verbose copy ("-c:v") (16));
+        assert_eq!(
+            program_and_args(&command),
+            (
+                OsStr::new("/stub/ffmpeg"),
+                ["-hide_banner", "--verbose", "copy", "-c:v", "16"]
+                    .map(OsStr::new)
+                    .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn exec_keeps_expressions_as_single_arguments() {
+        let title = "My Window";
+        let command = exec!(("xdotool") search ("--name") (title) (format!("{} {}", 1, 2)));
+        assert_eq!(
+            program_and_args(&command),
+            (
+                OsStr::new("/stub/xdotool"),
+                ["search", "--name", "My Window", "1 2"]
+                    .map(OsStr::new)
+                    .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn exec_appends_to_commands() {
+        let mut command = exec!(ffmpeg - y);
+        exec!(@(command) -f matroska (Path::new("a b.mkv").display()));
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["-y", "-f", "matroska", "a b.mkv"].map(OsStr::new)
+        );
+    }
+
+    #[test]
+    fn which_searches_each_directory() {
+        let command = which_in("sh", "/nonexistent:/bin:/usr/bin").expect("Find sh");
+        assert!(Path::new(command.get_program()).ends_with("sh"));
+        assert!(which_in("sh", "/nonexistent").is_none());
+        assert!(which_in("no-such-binary", "/bin:/usr/bin").is_none());
+    }
+
+    #[test]
+    fn ffmpeg_path_can_change_between_captures() {
+        let program = || which_in("ffmpeg", "/nonexistent").map(|c| c.get_program().to_owned());
+        use_ffmpeg(Some(PathBuf::from("/opt/a/ffmpeg")));
+        assert_eq!(program(), Some("/opt/a/ffmpeg".into()));
+        use_ffmpeg(Some(PathBuf::from("/opt/b/ffmpeg")));
+        assert_eq!(program(), Some("/opt/b/ffmpeg".into()));
+        use_ffmpeg(None);
+        assert_eq!(program(), None);
+    }
+
+    #[test]
+    fn numbered_paths() {
+        assert_eq!(
+            numbered_path(Path::new("/tmp/shot.png"), 2),
+            Path::new("/tmp/shot-2.png")
+        );
+        assert_eq!(
+            numbered_path(Path::new("host.2024-01-02.1200.00.png"), 3),
+            Path::new("host.2024-01-02.1200.00-3.png")
+        );
+        assert_eq!(numbered_path(Path::new("shot"), 1), Path::new("shot-1"));
+    }
+
+    #[test]
+    fn partial_paths() {
+        let path = Path::new("/tmp/Screenshot/shot.png");
+        let partial = partial_path(path).unwrap();
+        assert_eq!(partial, Path::new("/tmp/Screenshot/.partial.shot.png"));
+        assert_eq!(
+            partial.with_extension("opus"),
+            partial_path(&path.with_extension("opus")).unwrap()
+        );
+        assert_eq!(saved_path(&partial).as_deref(), Some(path));
+        assert_eq!(saved_path(path), None);
+        for path in &["/", "shots/.."] {
+            let error = partial_path(Path::new(path)).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn shell_quoting() {
+        assert_eq!(shell_quote("-video_size"), "-video_size");
+        assert_eq!(shell_quote(":0.0+10,20"), ":0.0+10,20");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("[0:v]crop=1:1[v]"), "'[0:v]crop=1:1[v]'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn shell_substitution() {
+        assert_eq!(
+            shell_substitute("scp {} host:shots/", "/tmp/a b.png"),
+            "scp '/tmp/a b.png' host:shots/"
+        );
+        assert_eq!(
+            shell_substitute("upload", "/tmp/a.png"),
+            "upload /tmp/a.png"
+        );
+    }
+}
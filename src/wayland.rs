@@ -0,0 +1,137 @@
+//! Capture on Wayland, where X11 capture doesn't work.
+//!
+//! Images are captured with `grim`, video with `wf-recorder` and selections
+//! are made with `slurp`. Wayland has no common way to find the focused window
+//! or output, so those regions are only supported on sway.
+
+use std::env::var_os;
+use std::io;
+use std::path::Path;
+use std::process::Stdio;
+
+use serde_json::Value;
+
+use crate::args::ScreenRegion::{self, *};
+use crate::util::*;
+use crate::{exec, info};
+
+/// Display servers that can be captured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    X11,
+    Wayland,
+}
+
+impl Backend {
+    /// Find which display server this session is using.
+    pub fn detect() -> Self {
+        if var_os("WAYLAND_DISPLAY").is_some() {
+            Backend::Wayland
+        } else {
+            Backend::X11
+        }
+    }
+}
+
+/// Get the geometry of a region as `X,Y WxH`, where none is the whole screen.
+fn wayland_region_string(region: ScreenRegion) -> io::Result<Option<String>> {
+    let found = |geometry: Option<String>, message: &str| {
+        geometry
+            .map(Some)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, message))
+    };
+    match region {
+        Screen | AllScreens => Ok(None),
+        Select => {
+            let geometry = command_output(exec!(slurp))?.next();
+            found(geometry, "No region was selected with slurp")
+        }
+        Geometry {
+            width,
+            height,
+            x,
+            y,
+        } => Ok(Some(format!("{},{} {}x{}", x, y, width, height))),
+        Window => found(
+            sway_focused("get_tree"),
+            "Could not find the focused window with sway",
+        ),
+        ActiveOutput => found(
+            sway_focused("get_outputs"),
+            "Could not find the focused output with sway",
+        ),
+    }
+}
+
+/// Get the geometry of the focused window or output from sway.
+fn sway_focused(query: &str) -> Option<String> {
+    let output: String = command_output(exec!(swaymsg - t(query) - r))
+        .ok()?
+        .collect();
+    let root: Value = serde_json::from_str(&output).ok()?;
+
+    // Windows are found by searching the tree of containers.
+    let mut nodes = vec![&root];
+    while let Some(node) = nodes.pop() {
+        if node["focused"].as_bool() == Some(true) {
+            let rect = &node["rect"];
+            return Some(format!(
+                "{},{} {}x{}",
+                rect["x"].as_i64()?,
+                rect["y"].as_i64()?,
+                rect["width"].as_u64()?,
+                rect["height"].as_u64()?
+            ));
+        }
+        for children in &["nodes", "floating_nodes"] {
+            nodes.extend(node[*children].as_array().into_iter().flatten());
+        }
+        nodes.extend(node.as_array().into_iter().flatten());
+    }
+    None
+}
+
+/// Capture an image of a region, or of a named output, with grim.
+pub fn capture_wayland_image(
+    filename: &Path,
+    region: ScreenRegion,
+    output: Option<&str>,
+) -> io::Result<()> {
+    let mut command = exec!(grim);
+    if let Some(output) = output {
+        exec!(@(command) -o (output));
+    } else if let Some(geometry) = wayland_region_string(region)? {
+        exec!(@(command) -g (geometry));
+    }
+    let status = command
+        .arg(filename)
+        .stdin(Stdio::null())
+        .status()
+        .map_err(running("grim"))?;
+    check_status(status, "take a screenshot with grim")
+}
+
+/// Record a region, or a named output, with wf-recorder until it is
+/// interrupted.
+pub fn capture_wayland_video(
+    filename: &Path,
+    region: ScreenRegion,
+    output: Option<&str>,
+    framerate: u64,
+) -> io::Result<()> {
+    let mut command = exec!(("wf-recorder") - a - r(framerate));
+    if let Some(output) = output {
+        exec!(@(command) -o (output));
+    } else if let Some(geometry) = wayland_region_string(region)? {
+        exec!(@(command) -g (geometry));
+    }
+    exec!(@(command) -f (filename.display()));
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(running("wf-recorder"))?;
+    info!("Started 'wf-recorder' with PID #{}", child.id());
+    check_status(child.wait()?, "record with wf-recorder")
+}